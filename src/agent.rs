@@ -0,0 +1,494 @@
+//! Local secrets agent.
+//!
+//! An [Agent] holds the Connect token and a warm cache, and serves secret
+//! references to local processes over a Unix socket, so only one process on
+//! a host needs the token.  Each request is one line of JSON, and so is each
+//! reply:
+//! ```text
+//! > {"reference": "op://prod/Postgres/password"}
+//! < {"value": "hunter2"}
+//! > {"reference": "op://prod/Stripe/key"}
+//! < {"error": "denied"}
+//! ```
+//! Callers are identified by the peer credentials of the socket, and the
+//! [AgentPolicy] allowlist decides which references each may read.  Every
+//! request is logged, allowed or not, at info level under the
+//! `op_connect_rs::agent` target.  The executable path of a caller is only
+//! known on Linux, and is read from `/proc` when the connection is accepted.
+//! A process can connect and then exec another binary, or pass the socket to
+//! a child that does, so an executable path only narrows a rule that also
+//! names a uid; it does not identify a caller on its own, and a rule with
+//! `exe` but no `uid` is rejected.
+//!
+//! The `op-agent` binary runs an agent; see its `--help`.
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use zeroize::Zeroizing;
+
+use crate::{Error, OpClient, Result, SecretReference};
+
+/// Longest request line accepted, in bytes.
+const MAX_REQUEST: u64 = 4096;
+
+/// The process on the other end of a connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Peer {
+    pub uid: u32,
+    pub gid: u32,
+    pub pid: Option<i32>,
+    pub exe: Option<PathBuf>,
+}
+
+impl std::fmt::Display for Peer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "uid={} gid={}", self.uid, self.gid)?;
+        if let Some(pid) = self.pid {
+            write!(f, " pid={}", pid)?;
+        }
+        if let Some(exe) = &self.exe {
+            write!(f, " exe={}", exe.display())?;
+        }
+        Ok(())
+    }
+}
+
+/// Which callers may read which references.  A request is allowed if any
+/// rule matches; an empty policy denies everything.
+///
+/// Policies are usually loaded from JSON:
+/// ```json
+/// {"allow": [
+///     {"uid": 33, "references": ["op://prod/nginx/"]},
+///     {"uid": 34, "exe": "/usr/bin/backup", "references": ["op://prod/S3/secret"]}
+/// ]}
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentPolicy {
+    #[serde(default)]
+    pub allow: Vec<AllowRule>,
+}
+
+/// A caller and the references it may read.  Unset criteria match any
+/// caller, and every set one must match.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AllowRule {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Executable path of the caller.  It can be faked by a process that
+    /// execs after connecting, so it needs `uid` too: a policy with a rule
+    /// that has `exe` but no `uid` fails to load, and the rule never matches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exe: Option<PathBuf>,
+    /// The references the caller may read.  `op://prod/S3/secret` allows that
+    /// one reference, and a trailing `/` allows everything under it, such as
+    /// every item in a vault with `op://prod/`.  Parts are compared whole, so
+    /// `op://prod/nginx/` does not allow `op://prod/nginx-admin/password`.
+    /// Empty allows every reference.
+    #[serde(default)]
+    pub references: Vec<String>,
+}
+
+impl AllowRule {
+    fn matches(&self, peer: &Peer, reference: &SecretReference) -> bool {
+        if self.exe.is_some() && self.uid.is_none() {
+            return false;
+        }
+        self.uid.is_none_or(|uid| uid == peer.uid)
+            && self.gid.is_none_or(|gid| gid == peer.gid)
+            && self
+                .exe
+                .as_ref()
+                .is_none_or(|exe| peer.exe.as_ref() == Some(exe))
+            && (self.references.is_empty()
+                || self
+                    .references
+                    .iter()
+                    .any(|pattern| pattern_matches(pattern, reference)))
+    }
+}
+
+/// Whether a reference pattern of a rule allows `reference`.  Patterns that
+/// are not `op://` references allow nothing.
+fn pattern_matches(pattern: &str, reference: &SecretReference) -> bool {
+    let Some(path) = pattern.strip_prefix("op://") else {
+        return false;
+    };
+    let (path, under) = match path.strip_suffix('/') {
+        Some(path) => (path, true),
+        None => (path, false),
+    };
+    let Ok(parts) = path
+        .split('/')
+        .map(|part| percent_decode_str(part).decode_utf8())
+        .collect::<std::result::Result<Vec<_>, _>>()
+    else {
+        return false;
+    };
+    if parts.iter().any(|part| part.is_empty()) {
+        return false;
+    }
+    let requested: Vec<&str> = [reference.vault.as_str(), reference.item.as_str()]
+        .into_iter()
+        .chain(reference.section.as_deref())
+        .chain([reference.field.as_str()])
+        .collect();
+    match under {
+        true => {
+            parts.len() < requested.len() && parts.iter().zip(&requested).all(|(part, r)| part == r)
+        }
+        false => {
+            parts.len() == requested.len()
+                && parts.iter().zip(&requested).all(|(part, r)| part == r)
+        }
+    }
+}
+
+impl AgentPolicy {
+    pub fn load(path: &Path) -> Result<Self> {
+        let policy: Self = serde_json::from_slice(&fs::read(path)?)?;
+        policy.check()?;
+        Ok(policy)
+    }
+
+    /// Reject rules that name an executable but no uid.
+    pub fn check(&self) -> Result<()> {
+        match self
+            .allow
+            .iter()
+            .position(|rule| rule.exe.is_some() && rule.uid.is_none())
+        {
+            Some(i) => Err(Error::InvalidPolicy(format!(
+                "rule {} names an exe but no uid",
+                i
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether `peer` may read `reference`.  References that do not parse
+    /// are denied.
+    pub fn allows(&self, peer: &Peer, reference: &str) -> bool {
+        let Ok(reference) = reference.parse::<SecretReference>() else {
+            return false;
+        };
+        self.allow.iter().any(|rule| rule.matches(peer, &reference))
+    }
+}
+
+#[derive(Deserialize)]
+struct AgentRequest {
+    reference: String,
+}
+
+#[derive(Serialize)]
+struct AgentReply<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Serves secrets to local processes.  See the [agent](crate::agent) module.
+#[derive(Debug, Clone)]
+pub struct Agent {
+    client: OpClient,
+    policy: AgentPolicy,
+    socket_mode: u32,
+}
+
+impl Agent {
+    /// The client should usually have a cache, see [OpClient::with_cache].
+    pub fn new(client: OpClient, policy: AgentPolicy) -> Self {
+        Self {
+            client,
+            policy,
+            socket_mode: 0o660,
+        }
+    }
+
+    /// Permissions of the socket file.  Defaults to 0660.
+    pub fn with_socket_mode(self, socket_mode: u32) -> Self {
+        Self {
+            socket_mode,
+            ..self
+        }
+    }
+
+    /// Listen on `path`, replacing a socket left behind by an earlier agent,
+    /// and serve until an error occurs.  Fails if anything other than a
+    /// socket is at `path`.  Must be called within a Tokio runtime.
+    pub async fn serve(self, path: &Path) -> Result<()> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path)?,
+            Ok(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", path.display()),
+                )
+                .into())
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        let listener = UnixListener::bind(path)?;
+        fs::set_permissions(path, fs::Permissions::from_mode(self.socket_mode))?;
+        self.serve_listener(listener).await
+    }
+
+    /// Serve connections from a listener, one task each.
+    pub async fn serve_listener(self, listener: UnixListener) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let agent = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = agent.serve_connection(stream).await {
+                    log::debug!("agent connection failed: {}", e);
+                }
+            });
+        }
+    }
+
+    async fn serve_connection(&self, stream: UnixStream) -> Result<()> {
+        let peer = peer(&stream)?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        loop {
+            let mut line = String::new();
+            let read = (&mut reader).take(MAX_REQUEST).read_line(&mut line).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            if !line.ends_with('\n') && read as u64 == MAX_REQUEST {
+                writer.write_all(&reply(Err("request too long"))).await?;
+                return Ok(());
+            }
+            writer.write_all(&self.answer(&peer, &line).await).await?;
+        }
+    }
+
+    /// The reply to one request line, newline included.
+    async fn answer(&self, peer: &Peer, line: &str) -> Zeroizing<Vec<u8>> {
+        let request = match serde_json::from_str::<AgentRequest>(line) {
+            Ok(request) => request,
+            Err(_) => {
+                log::info!(target: "op_connect_rs::agent", "{} invalid request", peer);
+                return reply(Err("invalid request"));
+            }
+        };
+        let reference = request.reference;
+        if !self.policy.allows(peer, &reference) {
+            log::info!(target: "op_connect_rs::agent", "{} denied {}", peer, reference);
+            return reply(Err("denied"));
+        }
+        match self.client.get_reference(&reference).await {
+            Ok(value) => {
+                log::info!(target: "op_connect_rs::agent", "{} read {}", peer, reference);
+                reply(Ok(value.expose_secret()))
+            }
+            Err(e) => {
+                log::info!(target: "op_connect_rs::agent", "{} failed {}: {}", peer, reference, e);
+                reply(Err(&failure(&e)))
+            }
+        }
+    }
+}
+
+/// A short reason for a failed read, which does not reveal more than the
+/// caller could learn from a 1Password client.
+fn failure(error: &Error) -> String {
+    match error {
+        Error::NotFound | Error::MissingField(_) => "not found".to_owned(),
+        Error::InvalidReference(_) => "invalid reference".to_owned(),
+        e if e.is_unavailable() => "unavailable".to_owned(),
+        _ => "failed".to_owned(),
+    }
+}
+
+fn reply(result: std::result::Result<&str, &str>) -> Zeroizing<Vec<u8>> {
+    let reply = match result {
+        Ok(value) => AgentReply {
+            value: Some(value),
+            error: None,
+        },
+        Err(error) => AgentReply {
+            value: None,
+            error: Some(error.to_owned()),
+        },
+    };
+    let mut line = Zeroizing::new(Vec::new());
+    // Serializing a struct of strings cannot fail.
+    let _ = serde_json::to_writer(&mut *line, &reply);
+    line.push(b'\n');
+    line
+}
+
+fn peer(stream: &UnixStream) -> Result<Peer> {
+    let credentials = stream.peer_cred()?;
+    let pid = credentials.pid();
+    Ok(Peer {
+        uid: credentials.uid(),
+        gid: credentials.gid(),
+        pid,
+        exe: pid.and_then(executable),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn executable(pid: i32) -> Option<PathBuf> {
+    fs::read_link(format!("/proc/{}/exe", pid)).ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn executable(_pid: i32) -> Option<PathBuf> {
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn peer(uid: u32, exe: &str) -> Peer {
+        Peer {
+            uid,
+            gid: 100,
+            pid: Some(42),
+            exe: Some(PathBuf::from(exe)),
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let policy: AgentPolicy = serde_json::from_str(
+            r#"{"allow": [
+                {"uid": 33, "references": ["op://prod/nginx/"]},
+                {"uid": 0, "exe": "/usr/bin/backup", "gid": 100}
+            ]}"#,
+        )
+        .unwrap();
+        policy.check().unwrap();
+        assert!(policy.allows(&peer(33, "/usr/sbin/nginx"), "op://prod/nginx/key"));
+        assert!(!policy.allows(&peer(33, "/usr/sbin/nginx"), "op://prod/db/password"));
+        assert!(policy.allows(&peer(0, "/usr/bin/backup"), "op://prod/db/password"));
+        assert!(!policy.allows(&peer(0, "/usr/bin/curl"), "op://prod/db/password"));
+        assert!(!AgentPolicy::default().allows(&peer(0, "/usr/bin/backup"), "op://x/y/z"));
+    }
+
+    #[test]
+    fn test_exe_needs_uid() {
+        let path = std::env::temp_dir().join(format!(
+            "op_connect_rs-agent-policy-{}.json",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            r#"{"allow": [{"exe": "/usr/bin/backup", "gid": 100}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            AgentPolicy::load(&path),
+            Err(Error::InvalidPolicy(_))
+        ));
+        fs::remove_file(&path).unwrap();
+
+        // Built in code, the rule still never matches.
+        let policy = AgentPolicy {
+            allow: vec![AllowRule {
+                exe: Some("/usr/bin/backup".into()),
+                ..AllowRule::default()
+            }],
+        };
+        assert!(policy.check().is_err());
+        assert!(!policy.allows(&peer(0, "/usr/bin/backup"), "op://prod/db/password"));
+    }
+
+    #[test]
+    fn test_reference_patterns() {
+        let policy: AgentPolicy = serde_json::from_str(
+            r#"{"allow": [
+                {"uid": 33, "references": ["op://prod/nginx/", "op://prod/S3/secret", "op://dev/"]},
+                {"uid": 34, "references": ["op://prod/db/admin%20password", "op://prod/nginx"]}
+            ]}"#,
+        )
+        .unwrap();
+        let nginx = peer(33, "/usr/sbin/nginx");
+        assert!(policy.allows(&nginx, "op://prod/nginx/key"));
+        assert!(policy.allows(&nginx, "op://prod/nginx/tls/key"));
+        assert!(!policy.allows(&nginx, "op://prod/nginx-admin/password"));
+        assert!(policy.allows(&nginx, "op://prod/S3/secret"));
+        assert!(!policy.allows(&nginx, "op://prod/S3/secretkey"));
+        assert!(!policy.allows(&nginx, "op://prod/S3/secret/anything"));
+        assert!(policy.allows(&nginx, "op://dev/db/password"));
+        assert!(!policy.allows(&nginx, "op://development/db/password"));
+        assert!(!policy.allows(&nginx, "op://prod/nginx/../db/password"));
+        assert!(!policy.allows(&nginx, "not a reference"));
+
+        let admin = peer(34, "/usr/bin/psql");
+        assert!(policy.allows(&admin, "op://prod/db/admin%20password"));
+        assert!(!policy.allows(&admin, "op://prod/db/admin"));
+        // Without a trailing slash, an item pattern names no field.
+        assert!(!policy.allows(&admin, "op://prod/nginx/key"));
+    }
+
+    #[tokio::test]
+    async fn test_serve() {
+        let dir = std::env::temp_dir().join(format!("op_connect_rs-agent-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("agent.sock");
+        let uid = std::os::unix::fs::MetadataExt::uid(&fs::metadata(&dir).unwrap());
+        let policy = AgentPolicy {
+            allow: vec![AllowRule {
+                uid: Some(uid),
+                references: vec!["op://dev/".to_owned()],
+                ..AllowRule::default()
+            }],
+        };
+        // Nothing listens on port 1, so allowed reads fail as unavailable.
+        let client = OpClient::new("http://127.0.0.1:1/v1/", "token").unwrap();
+        let listener = UnixListener::bind(&path).unwrap();
+        tokio::spawn(Agent::new(client, policy).serve_listener(listener));
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        for (request, expected) in [
+            (
+                r#"{"reference": "op://prod/db/password"}"#,
+                r#"{"error":"denied"}"#,
+            ),
+            (
+                r#"{"reference": "op://dev/db/password"}"#,
+                r#"{"error":"unavailable"}"#,
+            ),
+            ("not json", r#"{"error":"invalid request"}"#),
+        ] {
+            writer
+                .write_all(format!("{}\n", request).as_bytes())
+                .await
+                .unwrap();
+            assert_eq!(lines.next_line().await.unwrap().unwrap(), expected);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_serve_keeps_files() {
+        let path =
+            std::env::temp_dir().join(format!("op_connect_rs-agent-file-{}", std::process::id()));
+        fs::write(&path, b"not a socket").unwrap();
+        let client = OpClient::new("http://127.0.0.1:1/v1/", "token").unwrap();
+        let result = Agent::new(client, AgentPolicy::default())
+            .serve(&path)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(fs::read(&path).unwrap(), b"not a socket");
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,46 +1,220 @@
-use reqwest::{Client, Url};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use crate::{
+    handle::{field_value, refresh_state, Project},
+    watch::WatchState,
+    ItemChanged, SecretHandle,
+};
+#[cfg(feature = "async")]
+use futures::{stream, stream::BoxStream, Stream, StreamExt};
+use std::future::Future;
+#[cfg(feature = "async")]
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use reqwest::{
+    header::{HeaderValue, AUTHORIZATION},
+    Client, RequestBuilder, StatusCode, Url,
+};
 use serde::{Deserialize, Serialize};
 use zeroize::Zeroizing;
 
+use crate::cache::{detail_key, Cache, Lookup};
+use crate::cassette::{relative_path, Cassette, CassetteMode};
+#[cfg(not(feature = "async"))]
+use crate::disk_cache::persisted;
+#[cfg(feature = "async")]
+use crate::disk_cache::{fallback, stored};
+use crate::error::ApiErrorBody;
+use crate::model::secret_reference::unique_match;
+use crate::secure_memory::{reserve_zeroizing, MAX_PRESIZE};
+#[cfg(feature = "async")]
+use crate::single_flight::SingleFlight;
+use crate::{
+    CacheStats, DiskCache, Fetched, FieldValue, Item, ItemOverview, PatchOperation,
+    SecretReference, Vault, REDACTED,
+};
 use crate::{Error, Result};
-use crate::{Item, Vault};
 
-#[derive(Debug)]
+/// Clones share the HTTP connection pool and the caches.
+#[derive(Clone)]
 pub struct OpClient {
-    api_key: Zeroizing<String>,
+    /// The `Bearer` authorization header value, marked sensitive.
+    auth: HeaderValue,
     path: Url,
     client: Client,
+    cache: Option<Arc<Cache>>,
+    disk_cache: Option<Arc<DiskCache>>,
+    cassette: Option<Arc<Cassette>>,
+    /// GETs in flight, shared by concurrent callers.
+    #[cfg(feature = "async")]
+    in_flight: Arc<SingleFlight>,
+}
+
+impl std::fmt::Debug for OpClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpClient")
+            .field("auth", &format_args!("{}", REDACTED))
+            .field("path", &self.path.as_str())
+            .field("cached", &self.cache.is_some())
+            .field(
+                "disk_cache",
+                &self.disk_cache.as_ref().map(|disk_cache| disk_cache.dir()),
+            )
+            .field("cassette", &self.cassette.as_deref())
+            .finish()
+    }
 }
 
 impl OpClient {
     pub fn new(path: &str, api_key: &str) -> Result<Self> {
         let client = Client::new();
-        let api_key = Zeroizing::new(api_key.to_owned());
+        let auth = auth_header(api_key)?;
         let path = Url::try_from(path).map_err(|_e| Error::UrlParse)?;
         Ok(Self {
-            api_key,
+            auth,
             path,
             client,
+            cache: None,
+            disk_cache: None,
+            cassette: None,
+            #[cfg(feature = "async")]
+            in_flight: Arc::default(),
         })
     }
 
+    /// Cache vaults and items for `ttl`.  See the [cache](crate::cache)
+    /// module.
+    pub fn with_cache(self, ttl: Duration) -> Self {
+        Self {
+            cache: Some(Arc::new(Cache::new(ttl))),
+            ..self
+        }
+    }
+
+    /// Fall back to the last stored responses when Connect is unreachable.
+    /// See the [disk_cache](crate::disk_cache) module.
+    pub fn with_disk_cache(self, disk_cache: DiskCache) -> Self {
+        Self {
+            disk_cache: Some(Arc::new(disk_cache)),
+            ..self
+        }
+    }
+
+    /// Record requests to, or replay them from, a cassette.  See the
+    /// [cassette](crate::cassette) module.
+    pub fn with_cassette(self, cassette: Cassette) -> Self {
+        Self {
+            cassette: Some(Arc::new(cassette)),
+            ..self
+        }
+    }
+
+    /// Cache hit and miss counts, if the cache is enabled.
+    pub fn cache_stats(&self) -> Option<CacheStats> {
+        self.cache.as_ref().map(|cache| cache.stats())
+    }
+
+    /// Drop every cached entry.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+
     fn join(&self, part: &str) -> Result<Url> {
         self.path.join(part).map_err(|_e| Error::UrlParse)
     }
 
+    /// Send a request and read the whole body.  The returned future does not
+    /// borrow the client, so it can be shared.
+    fn fetch(
+        &self,
+        request: RequestBuilder,
+    ) -> impl Future<Output = Result<(StatusCode, Zeroizing<Vec<u8>>)>> + Send + 'static {
+        let request = request
+            .header("Accept", "application/json")
+            .header(AUTHORIZATION, self.auth.clone())
+            .build();
+        let client = self.client.clone();
+        let cassette = self.cassette.clone();
+        let base = self.path.clone();
+        async move {
+            let request = request?;
+            let Some(cassette) = cassette else {
+                return execute(&client, request).await;
+            };
+            let method = request.method().to_string();
+            let path = relative_path(base.as_str(), request.url().as_str()).to_owned();
+            let body = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map(<[u8]>::to_vec);
+            match cassette.mode() {
+                CassetteMode::Replay => {
+                    let (status, response) = cassette.find(&method, &path, body.as_deref())?;
+                    let status = StatusCode::from_u16(status).unwrap_or(StatusCode::BAD_GATEWAY);
+                    Ok((status, Zeroizing::new(response)))
+                }
+                CassetteMode::Record => {
+                    let (status, response) = execute(&client, request).await?;
+                    // Saving rewrites and fsyncs the whole cassette.
+                    #[cfg(feature = "async")]
+                    let response = {
+                        let (response, saved) = tokio::task::spawn_blocking(move || {
+                            let saved = cassette.save(
+                                &method,
+                                &path,
+                                body.as_deref(),
+                                status.as_u16(),
+                                &response,
+                            );
+                            (response, saved)
+                        })
+                        .await
+                        .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+                        saved?;
+                        response
+                    };
+                    #[cfg(not(feature = "async"))]
+                    cassette.save(&method, &path, body.as_deref(), status.as_u16(), &response)?;
+                    Ok((status, response))
+                }
+            }
+        }
+    }
+
+    async fn send<T: for<'a> Deserialize<'a>>(&self, request: RequestBuilder) -> Result<T> {
+        let (status, body) = self.fetch(request).await?;
+        parse_body(status, &body)
+    }
+
+    #[cfg(not(feature = "async"))]
     async fn get<T: for<'a> Deserialize<'a>>(&self, path: &str) -> Result<T> {
         let path = self.join(path)?;
-        let response = self
-            .client
-            .get(path)
-            .header("Accept", "application/json")
-            .bearer_auth((*self.api_key).clone())
-            .send()
-            .await?
-            .text()
+        self.send(self.client.get(path)).await
+    }
+
+    /// Concurrent GETs for the same path share one request.
+    #[cfg(feature = "async")]
+    async fn get<T: for<'a> Deserialize<'a>>(&self, path: &str) -> Result<T> {
+        let url = self.join(path)?;
+        let (status, body) = self
+            .in_flight
+            .run(url.as_str(), || {
+                let response = self.fetch(self.client.get(url.clone()));
+                async move {
+                    let (status, body) = response.await?;
+                    Ok((status, Arc::new(body)))
+                }
+            })
             .await?;
-        let result = serde_json::from_str::<T>(&response)?;
-        Ok(result)
+        parse_body(status, &body)
     }
 
     #[allow(dead_code)]
@@ -50,49 +224,532 @@ impl OpClient {
         body: &B,
     ) -> Result<T> {
         let path = self.join(path)?;
+        self.send(self.client.post(path).json(body)).await
+    }
 
-        let response = self
-            .client
-            .post(path)
-            .json(body)
-            .header("Accept", "application/json")
-            .bearer_auth((*self.api_key).clone())
-            .send()
-            .await?
-            .text()
-            .await?;
-        let result = serde_json::from_str::<T>(&response)?;
-        Ok(result)
+    async fn patch<T: for<'a> Deserialize<'a>, B: Serialize>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T> {
+        let path = self.join(path)?;
+        self.send(self.client.patch(path).json(body)).await
+    }
+
+    #[cfg(not(feature = "async"))]
+    async fn get_persisted<T: Serialize + for<'a> Deserialize<'a>>(
+        &self,
+        path: &str,
+    ) -> Result<Fetched<T>> {
+        let result = self.get::<T>(path).await;
+        persisted(self.disk_cache.as_deref(), path, result)
+    }
+
+    /// Disk cache reads and writes, which encrypt and fsync, run on the
+    /// blocking thread pool.
+    #[cfg(feature = "async")]
+    async fn get_persisted<T>(&self, path: &str) -> Result<Fetched<T>>
+    where
+        T: Serialize + for<'a> Deserialize<'a> + Send + 'static,
+    {
+        let result = self.get::<T>(path).await;
+        let Some(disk) = self.disk_cache.clone() else {
+            return result.map(Fetched::fresh);
+        };
+        let key = path.to_owned();
+        match result {
+            Ok(value) => {
+                let (value, result) = tokio::task::spawn_blocking(move || {
+                    let result = disk.store(&key, &value);
+                    (value, result)
+                })
+                .await
+                .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+                stored(result);
+                Ok(Fetched::fresh(value))
+            }
+            Err(e) if e.is_unavailable() => {
+                let loaded = tokio::task::spawn_blocking(move || disk.load::<T>(&key))
+                    .await
+                    .map_err(|e| Error::Io(std::io::Error::other(e)))?;
+                fallback(path, e, loaded)
+            }
+            Err(e) => Err(e),
+        }
     }
 
     pub async fn get_vaults(&self) -> Result<Vec<Vault>> {
-        self.get::<Vec<Vault>>("vaults").await
+        Ok(self.get_vaults_with_freshness().await?.value)
+    }
+
+    /// Like [get_vaults](Self::get_vaults), but says whether the vaults came
+    /// from the disk cache.
+    pub async fn get_vaults_with_freshness(&self) -> Result<Fetched<Vec<Vault>>> {
+        if let Some(vaults) = self.cache.as_ref().and_then(|cache| cache.vaults.get("")) {
+            return Ok(Fetched::fresh(vaults));
+        }
+        let vaults = self.get_persisted::<Vec<Vault>>("vaults").await?;
+        if let (Some(cache), false) = (&self.cache, vaults.is_stale()) {
+            cache
+                .vaults
+                .insert(String::new(), None, vaults.value.clone());
+        }
+        Ok(vaults)
     }
 
     pub async fn get_vault_by_id(&self, vault_id: &str) -> Result<Vault> {
-        self.get::<Vault>(&format!("vaults/{}", vault_id)).await
+        Ok(self.get_vault_by_id_with_freshness(vault_id).await?.value)
+    }
+
+    /// Like [get_vault_by_id](Self::get_vault_by_id), but says whether the
+    /// vault came from the disk cache.
+    pub async fn get_vault_by_id_with_freshness(&self, vault_id: &str) -> Result<Fetched<Vault>> {
+        if let Some(vault) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.vault.get(vault_id))
+        {
+            return Ok(Fetched::fresh(vault));
+        }
+        let vault = self
+            .get_persisted::<Vault>(&format!("vaults/{}", vault_id))
+            .await?;
+        if let (Some(cache), false) = (&self.cache, vault.is_stale()) {
+            cache
+                .vault
+                .insert(vault_id.to_owned(), None, vault.value.clone());
+        }
+        Ok(vault)
     }
 
     pub async fn get_vault_by_name(&self, name: &str) -> Result<Vault> {
+        Ok(self.get_vault_by_name_with_freshness(name).await?.value)
+    }
+
+    /// Like [get_vault_by_name](Self::get_vault_by_name), but says whether
+    /// the vault came from the disk cache.
+    pub async fn get_vault_by_name_with_freshness(&self, name: &str) -> Result<Fetched<Vault>> {
         let vaults = self
-            .get::<Vec<Vault>>(&format!("vaults?name=\"{}\"", name))
+            .get_persisted::<Vec<Vault>>(&format!("vaults?name=\"{}\"", name))
             .await?;
         if vaults.len() == 1 {
-            Ok(vaults[0].clone())
+            Ok(Fetched {
+                value: vaults.value[0].clone(),
+                freshness: vaults.freshness,
+            })
         } else {
             // this is an error
             Err(Error::NotFound)
         }
     }
 
-    pub async fn get_items(&self, vault: &Vault) -> Result<Vec<Item>> {
+    pub async fn get_items(&self, vault: &Vault) -> Result<Vec<ItemOverview>> {
+        Ok(self.get_items_with_freshness(vault).await?.value)
+    }
+
+    /// Like [get_items](Self::get_items), but says whether the items came
+    /// from the disk cache.
+    pub async fn get_items_with_freshness(
+        &self,
+        vault: &Vault,
+    ) -> Result<Fetched<Vec<ItemOverview>>> {
         let path = format!("vaults/{}/items", vault.id);
-        self.get::<Vec<Item>>(&path).await
+        let Some(cache) = &self.cache else {
+            return self.get_persisted::<Vec<ItemOverview>>(&path).await;
+        };
+        let content_version = match cache.items.lookup(&vault.id) {
+            Lookup::Fresh(items) => return Ok(Fetched::fresh(items)),
+            Lookup::Expired => {
+                let content_version = self.content_version(&vault.id).await?;
+                if let Some(items) = cache.items.revalidate(&vault.id, content_version) {
+                    return Ok(Fetched::fresh(items));
+                }
+                content_version
+            }
+            Lookup::Miss => vault.content_version,
+        };
+        let items = self.get_persisted::<Vec<ItemOverview>>(&path).await?;
+        if !items.is_stale() {
+            cache
+                .items
+                .insert(vault.id.clone(), content_version, items.value.clone());
+        }
+        Ok(items)
     }
 
     pub async fn get_item_detail(&self, vault: &Vault, item_id: &str) -> Result<Item> {
+        Ok(self
+            .get_item_detail_with_freshness(vault, item_id)
+            .await?
+            .value)
+    }
+
+    /// Like [get_item_detail](Self::get_item_detail), but says whether the
+    /// item came from the disk cache.
+    pub async fn get_item_detail_with_freshness(
+        &self,
+        vault: &Vault,
+        item_id: &str,
+    ) -> Result<Fetched<Item>> {
         let path = format!("vaults/{}/items/{}", vault.id, item_id);
-        self.get::<Item>(&path).await
+        let Some(cache) = &self.cache else {
+            return self.get_persisted::<Item>(&path).await;
+        };
+        let key = detail_key(&vault.id, item_id);
+        let content_version = match cache.details.lookup(&key) {
+            Lookup::Fresh(item) => return Ok(Fetched::fresh(item)),
+            Lookup::Expired => {
+                let content_version = self.content_version(&vault.id).await?;
+                if let Some(item) = cache.details.revalidate(&key, content_version) {
+                    return Ok(Fetched::fresh(item));
+                }
+                content_version
+            }
+            Lookup::Miss => vault.content_version,
+        };
+        let item = self.get_persisted::<Item>(&path).await?;
+        if !item.is_stale() {
+            cache
+                .details
+                .insert(key, content_version, item.value.clone());
+        }
+        Ok(item)
+    }
+
+    /// The vault's current content version, used to revalidate cached items.
+    /// The vault is always fetched from Connect, as a cached one could be
+    /// older than the entry being revalidated.  If Connect is unreachable it
+    /// gives `None`, so the caller falls back to the disk cache.
+    async fn content_version(&self, vault_id: &str) -> Result<Option<u32>> {
+        let vault = match self.get::<Vault>(&format!("vaults/{}", vault_id)).await {
+            Ok(vault) => vault,
+            Err(e) if e.is_unavailable() => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        if let Some(cache) = &self.cache {
+            cache.vault.insert(vault_id.to_owned(), None, vault.clone());
+        }
+        Ok(vault.content_version)
+    }
+
+    /// List a vault and fetch every item's detail, with up to `concurrency`
+    /// requests in flight.  Items are returned as they arrive, not in listing
+    /// order.
+    #[cfg(feature = "async")]
+    pub async fn get_items_detailed<'a>(
+        &'a self,
+        vault: &'a Vault,
+        concurrency: usize,
+    ) -> Result<impl Stream<Item = Result<Item>> + 'a> {
+        let overviews = self.get_items(vault).await?;
+        Ok(stream::iter(overviews)
+            .map(move |overview| async move { self.get_item_detail(vault, &overview.id).await })
+            .buffer_unordered(concurrency.max(1)))
+    }
+
+    /// Watch items for changes.  See the [watch](crate::watch) module.
+    #[cfg(feature = "async")]
+    pub fn watch(&self, vault: &Vault, items: &[Item]) -> Watch {
+        Watch {
+            client: self.clone(),
+            state: Some(WatchState::new(vault, items)),
+            stream: None,
+        }
+    }
+
+    /// Poll the vault once, and refetch the watched items that moved.  The
+    /// caches are bypassed, and the state is only updated once every refetch
+    /// has succeeded.
+    #[cfg(feature = "async")]
+    async fn poll_changes(&self, state: &mut WatchState) -> Result<Vec<ItemChanged>> {
+        let vault = self
+            .get::<Vault>(&format!("vaults/{}", state.vault_id))
+            .await?;
+        if !state.vault_changed(&vault) {
+            return Ok(Vec::new());
+        }
+        let overviews = self
+            .get::<Vec<ItemOverview>>(&format!("vaults/{}/items", state.vault_id))
+            .await?;
+        let mut items = Vec::new();
+        for item_id in state.moved(&overviews) {
+            let path = format!("vaults/{}/items/{}", state.vault_id, item_id);
+            items.push(self.get::<Item>(&path).await?);
+            if let Some(cache) = &self.cache {
+                cache.invalidate_item(&state.vault_id, &item_id);
+            }
+        }
+        Ok(state.apply(items, vault.content_version))
+    }
+
+    /// A handle to a typed view of an item, such as a `DatabaseItem`, which
+    /// is checked for changes every `refresh`.  See the
+    /// [handle](crate::handle) module.  Must be called within a Tokio
+    /// runtime, which runs the refresh task.
+    #[cfg(feature = "async")]
+    pub async fn item_handle<T>(
+        &self,
+        vault: &Vault,
+        item_id: &str,
+        refresh: Duration,
+    ) -> Result<SecretHandle<T>>
+    where
+        T: for<'a> TryFrom<&'a Item, Error = Error> + Send + Sync + 'static,
+    {
+        let item = self.get_item_detail(vault, item_id).await?;
+        self.spawn_handle(
+            vault,
+            &item,
+            None,
+            Box::new(|item| T::try_from(item)),
+            refresh,
+        )
+    }
+
+    /// A handle to the value of a field, which is checked for changes every
+    /// `refresh`.  Must be called within a Tokio runtime, which runs the
+    /// refresh task.
+    #[cfg(feature = "async")]
+    pub async fn field_handle(
+        &self,
+        vault: &Vault,
+        item_id: &str,
+        field_id: &str,
+        refresh: Duration,
+    ) -> Result<SecretHandle<FieldValue>> {
+        let item = self.get_item_detail(vault, item_id).await?;
+        let field_id = field_id.to_owned();
+        self.spawn_handle(
+            vault,
+            &item,
+            Some(field_id.clone()),
+            field_value(field_id),
+            refresh,
+        )
+    }
+
+    /// A handle to the value of the field named by a secret reference,
+    /// `op://vault/item/[section/]field`, which is checked for changes every
+    /// `refresh`.  Must be called within a Tokio runtime, which runs the
+    /// refresh task.
+    #[cfg(feature = "async")]
+    pub async fn reference_handle(
+        &self,
+        reference: &str,
+        refresh: Duration,
+    ) -> Result<SecretHandle<FieldValue>> {
+        let reference: SecretReference = reference.parse()?;
+        let (vault, item) = self.find_reference(&reference).await?;
+        let field_id = reference.field(&item)?.id.clone();
+        self.spawn_handle(
+            &vault,
+            &item,
+            Some(field_id.clone()),
+            field_value(field_id),
+            refresh,
+        )
+    }
+
+    #[cfg(feature = "async")]
+    fn spawn_handle<T: Send + Sync + 'static>(
+        &self,
+        vault: &Vault,
+        item: &Item,
+        field_id: Option<String>,
+        project: Project<T>,
+        refresh: Duration,
+    ) -> Result<SecretHandle<T>> {
+        let handle = SecretHandle::new(item, field_id, project)?;
+        let mut state = refresh_state(vault, item, refresh);
+        let client = self.clone();
+        let weak = handle.downgrade();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(state.delay()).await;
+                let Some(handle) = weak.upgrade() else {
+                    break;
+                };
+                match client.poll_changes(&mut state).await {
+                    Ok(changes) => changes.iter().for_each(|change| handle.changed(change)),
+                    Err(e) => log::warn!("failed to refresh secret handle: {}", e),
+                }
+            }
+        });
+        Ok(handle)
+    }
+
+    /// Download the content of a file attached to an item.  File contents
+    /// are not cached.
+    pub async fn get_file_content(&self, item: &Item, file_id: &str) -> Result<Zeroizing<Vec<u8>>> {
+        let path = self.join(&format!(
+            "vaults/{}/items/{}/files/{}/content",
+            item.vault.id, item.id, file_id
+        ))?;
+        let (status, body) = self.fetch(self.client.get(path)).await?;
+        check_status(status, &body)?;
+        Ok(body)
+    }
+
+    /// The value of the field named by a secret reference,
+    /// `op://vault/item/[section/]field`.  A field without a value is
+    /// [Error::NotFound], like a missing one.
+    pub async fn get_reference(&self, reference: &str) -> Result<FieldValue> {
+        let reference: SecretReference = reference.parse()?;
+        let (_, item) = self.find_reference(&reference).await?;
+        reference.field(&item)?.value.clone().ok_or(Error::NotFound)
+    }
+
+    /// The vault and item a reference names.  Each must match exactly one.
+    async fn find_reference(&self, reference: &SecretReference) -> Result<(Vault, Item)> {
+        let vaults = self.get_vaults().await?;
+        let vault = unique_match(
+            vaults.iter().filter(|vault| reference.matches_vault(vault)),
+            reference,
+        )?;
+        let items = self.get_items(vault).await?;
+        let overview = unique_match(
+            items.iter().filter(|item| reference.matches_item(item)),
+            reference,
+        )?;
+        let item = self.get_item_detail(vault, &overview.id).await?;
+        Ok((vault.clone(), item))
+    }
+
+    /// Apply JSON Patch operations to an item, and return the updated item.
+    pub async fn patch_item(&self, item: &Item, operations: &[PatchOperation]) -> Result<Item> {
+        let path = format!("vaults/{}/items/{}", item.vault.id, item.id);
+        let updated = self.patch::<Item, _>(&path, &operations).await;
+        if let Some(cache) = &self.cache {
+            cache.invalidate_item(&item.vault.id, &item.id);
+        }
+        updated
+    }
+}
+
+/// Changes reported by [OpClient::watch].  A failed poll is yielded as an
+/// error, and watching carries on; the stream never ends.
+#[cfg(feature = "async")]
+pub struct Watch {
+    client: OpClient,
+    /// Taken when the stream is first polled.
+    state: Option<WatchState>,
+    stream: Option<BoxStream<'static, Result<ItemChanged>>>,
+}
+
+#[cfg(feature = "async")]
+impl Watch {
+    /// Time between polls.  Defaults to 30 seconds.  Has no effect once the
+    /// stream has been polled.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        if let Some(state) = &mut self.state {
+            state.interval = interval;
+        }
+        self
+    }
+
+    /// Upper bound of the random delay added to each poll.  Defaults to 5
+    /// seconds.  Has no effect once the stream has been polled.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        if let Some(state) = &mut self.state {
+            state.jitter = jitter;
+        }
+        self
+    }
+}
+
+#[cfg(feature = "async")]
+impl Stream for Watch {
+    type Item = Result<ItemChanged>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        if let Some(state) = this.state.take() {
+            this.stream = Some(watch_changes(this.client.clone(), state));
+        }
+        match &mut this.stream {
+            Some(stream) => stream.poll_next_unpin(cx),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+fn watch_changes(client: OpClient, state: WatchState) -> BoxStream<'static, Result<ItemChanged>> {
+    stream::unfold(
+        (client, state, VecDeque::new()),
+        |(client, mut state, mut pending)| async move {
+            loop {
+                if let Some(change) = pending.pop_front() {
+                    return Some((Ok(change), (client, state, pending)));
+                }
+                tokio::time::sleep(state.delay()).await;
+                match client.poll_changes(&mut state).await {
+                    Ok(changes) => pending.extend(changes),
+                    Err(e) => return Some((Err(e), (client, state, pending))),
+                }
+            }
+        },
+    )
+    .boxed()
+}
+
+/// Send a request and read the whole body into a zeroizing buffer.
+async fn execute(
+    client: &Client,
+    request: reqwest::Request,
+) -> Result<(StatusCode, Zeroizing<Vec<u8>>)> {
+    let mut response = client.execute(request).await?;
+    let status = response.status();
+    let presize = response.content_length().unwrap_or(0).min(MAX_PRESIZE);
+    let mut body = Zeroizing::new(Vec::with_capacity(presize as usize));
+    while let Some(chunk) = response.chunk().await? {
+        reserve_zeroizing(&mut body, chunk.len());
+        body.extend_from_slice(&chunk);
+    }
+    Ok((status, body))
+}
+
+/// Build the authorization header without leaving unzeroized copies of the
+/// token behind.  The value is marked sensitive so it is not logged, but the
+/// `HeaderValue`, which holds the token for the life of the client, and the
+/// copies reqwest makes of it for each request cannot be wiped.
+fn auth_header(api_key: &str) -> Result<HeaderValue> {
+    let mut bearer = Zeroizing::new(String::with_capacity(7 + api_key.len()));
+    bearer.push_str("Bearer ");
+    bearer.push_str(api_key);
+    let mut auth = HeaderValue::from_str(&bearer).map_err(|_e| Error::InvalidToken)?;
+    auth.set_sensitive(true);
+    Ok(auth)
+}
+
+/// Deserialize a response body, which the caller wipes once done.  Connect
+/// reports errors as `{"status": 401, "message": "..."}`.
+fn parse_body<T: for<'a> Deserialize<'a>>(status: StatusCode, body: &[u8]) -> Result<T> {
+    check_status(status, body)?;
+    let result = serde_json::from_slice::<T>(body)?;
+    Ok(result)
+}
+
+fn check_status(status: StatusCode, body: &[u8]) -> Result<()> {
+    if status == StatusCode::NOT_FOUND {
+        return Err(Error::NotFound);
+    }
+    if !status.is_success() {
+        let message = serde_json::from_slice::<ApiErrorBody>(body)
+            .map(|error| error.message)
+            .unwrap_or_default();
+        return Err(Error::Api {
+            status: status.as_u16(),
+            message,
+        });
+    }
+    Ok(())
+}
+
+impl ItemOverview {
+    /// Fetch the full item, including fields, files and sections.
+    pub async fn fetch_detail(&self, client: &OpClient) -> Result<Item> {
+        client.get_item_detail(&self.vault, &self.id).await
     }
 }
 
@@ -101,28 +758,52 @@ mod test {
 
     use super::*;
 
-    fn get_client() -> OpClient {
-        let path = std::env::var("OP_PATH").unwrap_or("http://localhost:8080/v1/".to_owned());
-        let api_key = std::env::var("OP_API_TOKEN").expect("OP_API_TOKEN not set");
+    #[test]
+    fn test_debug_redacts_api_key() {
+        let client = OpClient::new(
+            "http://localhost:8080/v1/",
+            "eyJhbGciOiJFUzI1NiIsImtpZCI6InRva2VuIn0",
+        )
+        .expect("failed to create client");
+        assert!(!format!("{:?}", client).contains("eyJhbGciOiJFUzI1NiIsImtpZCI6InRva2VuIn0"));
+    }
+
+    use crate::testing::{Fault, MockServer};
+    use crate::{
+        ChainStore, CharacterSet, EnvStore, GeneratorRecipe, InMemoryStore, Rotator, SecretStore,
+        VaultAudit,
+    };
+    use std::sync::Mutex;
 
-        OpClient::new(&path, &api_key).expect("failed to create client")
+    fn get_server() -> MockServer {
+        let server = MockServer::start();
+        server.add_vault(
+            serde_json::from_str(include_str!("../tests/fixtures/dev_vault.json")).unwrap(),
+        );
+        server.add_item(
+            serde_json::from_str(include_str!("../tests/fixtures/okta_item.json")).unwrap(),
+        );
+        server.add_file_content("6r65pjq33banznomn7q22sj44e", b"-----BEGIN-");
+        server
     }
 
     #[tokio::test]
     async fn test_get_items() {
-        let client = get_client();
+        let server = get_server();
+        let client = server.client();
         let vault = client
             .get_vault_by_name("dev")
             .await
             .expect("failed to get vault");
-        let result = client.get_items(&vault).await;
-        assert!(result.is_ok());
-        println!("Result: {:#?}", &result.unwrap());
+        let items = client.get_items(&vault).await.expect("failed to get items");
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "okta");
     }
 
     #[tokio::test]
     async fn test_get_item_detail() {
-        let client = get_client();
+        let server = get_server();
+        let client = server.client();
         let vault = client
             .get_vault_by_name("dev")
             .await
@@ -133,42 +814,584 @@ mod test {
             .get_item_detail(&vault, &item.id)
             .await
             .expect("failed to get items");
-        //println!("Item Details: {:#?}", &item);
-        println!(
-            "Username: {}",
-            *item_detail.get_field_by_id("username").unwrap()
-        );
-        println!(
-            "Credential: {:?}",
-            *item_detail.get_field_by_id("credential").unwrap()
+        assert_eq!(
+            item_detail
+                .get_field_by_id("username")
+                .unwrap()
+                .expose_secret(),
+            "okta-admin"
         );
-        println!(
-            "Okta App: {:?}",
-            *item_detail.get_field_by_label("app").unwrap()
+        assert!(item_detail.get_field_by_id("credential").is_some());
+        assert_eq!(
+            item_detail
+                .get_field_by_label("app")
+                .unwrap()
+                .expose_secret(),
+            "dev-123456.okta.com"
         );
     }
 
     #[tokio::test]
     async fn test_vaults() {
-        println!(
-            "Vaults: {:#?}",
-            get_client().get_vaults().await.expect("oops")
-        )
+        let server = get_server();
+        let vaults = server.client().get_vaults().await.expect("oops");
+        assert_eq!(vaults.len(), 1);
+        assert!(server.requests().iter().all(|r| r.authorized));
     }
 
     #[tokio::test]
     async fn test_vault_by_id() {
-        let client = get_client();
-        let vault = client.get_vault_by_id("2me2svdjkmsh5xmqodspni4h6a").await;
-        println!("Result: {:#?}", vault);
-        //println!("Vault: {:#?}", &vault);
+        let server = get_server();
+        let client = server.client();
+        let vault = client
+            .get_vault_by_id("2me2svdjkmsh5xmqodspni4h6a")
+            .await
+            .expect("failed to get vault");
+        assert_eq!(vault.name, "dev");
+        assert!(matches!(
+            client.get_vault_by_id("nosuchvault").await,
+            Err(Error::NotFound)
+        ));
     }
 
     #[tokio::test]
     async fn test_vault_by_name() {
-        let client = get_client();
-        let vault = client.get_vault_by_name("dev").await;
-        println!("Result: {:#?}", vault);
-        //println!("Vault: {:#?}", &vault);
+        let server = get_server();
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").await.expect("no vault");
+        assert_eq!(vault.id, "2me2svdjkmsh5xmqodspni4h6a");
+        assert!(matches!(
+            client.get_vault_by_name("prod").await,
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let server = get_server();
+        let client = OpClient::new(&server.url(), "wrong").unwrap();
+        assert!(matches!(
+            client.get_vaults().await,
+            Err(Error::Api { status: 401, .. })
+        ));
+        assert!(!server.requests()[0].authorized);
+
+        let client = server.client();
+        server.inject(Fault::Status(429));
+        server.inject(Fault::Status(500));
+        server.inject(Fault::MalformedJson);
+        assert!(matches!(
+            client.get_vaults().await,
+            Err(Error::Api { status: 429, .. })
+        ));
+        assert!(client.get_vaults().await.unwrap_err().is_unavailable());
+        assert!(matches!(
+            client.get_vaults().await,
+            Err(Error::SerdeJson(_))
+        ));
+        assert!(client.get_vaults().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_files_and_patch() {
+        let server = get_server();
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        let content = client
+            .get_file_content(&item, "6r65pjq33banznomn7q22sj44e")
+            .await
+            .unwrap();
+        assert_eq!(content.as_slice(), b"-----BEGIN-");
+
+        let updated = client
+            .patch_item(
+                &item,
+                &[PatchOperation::replace_field_value(
+                    "credential",
+                    FieldValue::new("rotated"),
+                )],
+            )
+            .await
+            .unwrap();
+        assert_eq!(updated.version, item.version + 1);
+        assert_eq!(
+            client
+                .get_reference("op://dev/okta/credential")
+                .await
+                .unwrap()
+                .expose_secret(),
+            "rotated"
+        );
+        let vault = client.get_vault_by_id(&vault.id).await.unwrap();
+        assert_eq!(vault.content_version, Some(4));
+    }
+
+    /// A rotator that logs each hook call, with the value, to `calls`.  The
+    /// hook named `failing`, if any, returns an error.
+    fn logging_rotator(
+        calls: &Arc<Mutex<Vec<(&'static str, String)>>>,
+        failing: &'static str,
+    ) -> Rotator {
+        let hook = |name: &'static str| {
+            let calls = calls.clone();
+            let fails = name == failing;
+            move |value: FieldValue| {
+                let calls = calls.clone();
+                async move {
+                    calls
+                        .lock()
+                        .unwrap()
+                        .push((name, value.expose_secret().to_owned()));
+                    if fails {
+                        Err("hook failed")
+                    } else {
+                        Ok(())
+                    }
+                }
+            }
+        };
+        let recipe = GeneratorRecipe {
+            character_sets: vec![CharacterSet::Letters, CharacterSet::Digits],
+            length: 24,
+            exclude_characteres: None,
+        };
+        Rotator::new(recipe)
+            .on_apply(hook("apply"))
+            .on_commit(hook("commit"))
+            .on_rollback(hook("rollback"))
+    }
+
+    #[tokio::test]
+    async fn test_rotate() {
+        const OLD: &str = "00Qk3h6rX2mJ8vYbW1tN5pL9cZ4dF7gH0sA2eR";
+        let server = get_server();
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+
+        // The PATCH fails, so the old value is restored on the target.
+        server.inject(Fault::Latency(Duration::ZERO));
+        server.inject(Fault::Status(500));
+        let result = logging_rotator(&calls, "")
+            .rotate(&client, &item, "credential")
+            .await;
+        assert!(matches!(result, Err(Error::Api { status: 500, .. })));
+        let logged = std::mem::take(&mut *calls.lock().unwrap());
+        assert_eq!(logged.len(), 2);
+        assert_eq!(logged[0].0, "apply");
+        assert_ne!(logged[0].1, OLD);
+        assert_eq!(logged[1], ("rollback", OLD.to_owned()));
+        assert_eq!(
+            client
+                .get_reference("op://dev/okta/credential")
+                .await
+                .unwrap()
+                .expose_secret(),
+            OLD
+        );
+
+        // The rollback fails too, and the error carries the new value.
+        server.inject(Fault::Latency(Duration::ZERO));
+        server.inject(Fault::Status(500));
+        let result = logging_rotator(&calls, "rollback")
+            .rotate(&client, &item, "credential")
+            .await;
+        let logged = std::mem::take(&mut *calls.lock().unwrap());
+        match result {
+            Err(Error::RollbackFailed {
+                write, new_value, ..
+            }) => {
+                assert!(matches!(*write, Error::Api { status: 500, .. }));
+                assert_eq!(new_value.expose_secret(), logged[0].1);
+            }
+            other => panic!("expected a failed rollback, got {:?}", other),
+        }
+        assert_eq!(logged[1], ("rollback", OLD.to_owned()));
+
+        // Both sides take the new value, and the commit hook runs.
+        let rotation = logging_rotator(&calls, "")
+            .rotate(&client, &item, "credential")
+            .await
+            .unwrap();
+        let logged = std::mem::take(&mut *calls.lock().unwrap());
+        assert_eq!(rotation.item.version, item.version + 1);
+        assert_eq!(logged.len(), 2);
+        assert_eq!(logged[0].0, "apply");
+        assert_eq!(logged[1], ("commit", logged[0].1.clone()));
+        assert_eq!(
+            client
+                .get_reference("op://dev/okta/credential")
+                .await
+                .unwrap()
+                .expose_secret(),
+            logged[0].1
+        );
+
+        // The commit hook fails after both sides have the new value.
+        let result = logging_rotator(&calls, "commit")
+            .rotate(&client, &rotation.item, "credential")
+            .await;
+        let logged = std::mem::take(&mut *calls.lock().unwrap());
+        match result {
+            Err(Error::CommitFailed {
+                rotation: failed, ..
+            }) => {
+                assert_eq!(failed.item.version, rotation.item.version + 1);
+            }
+            other => panic!("expected a failed commit, got {:?}", other),
+        }
+        assert_eq!(logged[1], ("commit", logged[0].1.clone()));
+    }
+
+    /// Add `count` copies of the okta item, with their own ids, to the dev
+    /// vault.
+    #[cfg(feature = "async")]
+    fn add_copies(server: &MockServer, count: usize) {
+        let okta: Item =
+            serde_json::from_str(include_str!("../tests/fixtures/okta_item.json")).unwrap();
+        for i in 0..count {
+            let mut copy = okta.clone();
+            copy.id = format!("copy{:022}", i);
+            copy.title = format!("okta {}", i);
+            server.add_item(copy);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_get_items_detailed() {
+        let server = get_server();
+        add_copies(&server, 7);
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        // Latency keeps the requests of a round in flight together.
+        server.set_latency(Duration::from_millis(50));
+
+        // 8 items, 2 at a time.
+        server.clear_requests();
+        let items: Vec<_> = client
+            .get_items_detailed(&vault, 2)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(server.max_in_flight(), 2);
+        let mut ids: Vec<_> = items.into_iter().map(|item| item.unwrap().id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 8);
+
+        // All at once.
+        server.clear_requests();
+        let items: Vec<_> = client
+            .get_items_detailed(&vault, 8)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert!(server.max_in_flight() > 2);
+        assert!(server.max_in_flight() <= 8);
+        assert_eq!(items.len(), 8);
+
+        // One item fails, and the others still arrive.
+        server.set_latency(Duration::ZERO);
+        server.inject(Fault::Latency(Duration::ZERO));
+        server.inject(Fault::Status(500));
+        let items: Vec<_> = client
+            .get_items_detailed(&vault, 3)
+            .await
+            .unwrap()
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(items.len(), 8);
+        assert_eq!(items.iter().filter(|item| item.is_err()).count(), 1);
+        assert!(items
+            .iter()
+            .any(|item| matches!(item, Err(Error::Api { status: 500, .. }))));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_watch() {
+        let server = get_server();
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        let mut changes = client
+            .watch(&vault, std::slice::from_ref(&item))
+            .with_interval(Duration::from_millis(20))
+            .with_jitter(Duration::ZERO);
+        let rotate = |item: Item, value: &'static str| {
+            let client = client.clone();
+            async move {
+                let operations = [PatchOperation::replace_field_value(
+                    "credential",
+                    FieldValue::new(value),
+                )];
+                client.patch_item(&item, &operations).await.unwrap()
+            }
+        };
+
+        // Changed before the first poll.
+        let updated = rotate(item.clone(), "rotated").await;
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.old_version, item.version);
+        assert_eq!(change.new.version, updated.version);
+        assert_eq!(change.changed_fields, vec!["credential"]);
+        assert_eq!(
+            change
+                .new
+                .get_field_by_id("credential")
+                .unwrap()
+                .expose_secret(),
+            "rotated"
+        );
+
+        // Polls that find the vault unchanged do not list it.
+        server.clear_requests();
+        let rotation = rotate(updated, "rotated again");
+        let writer = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            rotation.await
+        });
+        let change = tokio::time::timeout(Duration::from_secs(5), changes.next())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        assert_eq!(change.new.version, writer.await.unwrap().version);
+        let requests = server.requests();
+        let lists = requests
+            .iter()
+            .filter(|r| r.method == "GET" && r.path.ends_with("/items"))
+            .count();
+        assert_eq!(lists, 1);
+        assert!(requests.len() > 4);
+
+        // A failed poll is reported as an error.
+        server.inject(Fault::Status(500));
+        assert!(changes.next().await.unwrap().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_audit_run() {
+        let server = get_server();
+        let login: Item =
+            serde_json::from_str(include_str!("../tests/fixtures/corpus/items/login.json"))
+                .unwrap();
+        let mut copy = login.clone();
+        copy.id = "ckfrbe3xiujgtlqzlusugl5jla".to_owned();
+        server.add_item(login);
+        server.add_item(copy);
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        let report = VaultAudit::default().run(&client, &vault).await.unwrap();
+        assert_eq!(report.items_checked, 3);
+        let reused = report
+            .findings
+            .iter()
+            .filter(|finding| matches!(finding.kind, crate::FindingKind::ReusedPassword { .. }))
+            .count();
+        assert_eq!(reused, 2);
+    }
+
+    #[tokio::test]
+    async fn test_revalidate_against_connect() {
+        let server = get_server();
+        let ttl = Duration::from_millis(200);
+        let client = server.client().with_cache(ttl);
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        tokio::time::sleep(ttl * 3 / 5).await;
+        // Cache the vault, then change the item behind the client's back.
+        client.get_vault_by_id(&vault.id).await.unwrap();
+        server
+            .client()
+            .patch_item(
+                &item,
+                &[PatchOperation::replace_field_value(
+                    "credential",
+                    FieldValue::new("rotated"),
+                )],
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(ttl * 3 / 5).await;
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        assert_eq!(
+            item.get_field_by_id("credential").unwrap().expose_secret(),
+            "rotated"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cassette() {
+        let server = get_server();
+        let path = std::env::temp_dir().join(format!(
+            "op_connect_rs-async-cassette-{}.json",
+            std::process::id()
+        ));
+        let recording = server.client().with_cassette(Cassette::record(&path));
+        let vault = recording.get_vault_by_name("dev").await.unwrap();
+        let item = recording
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        recording
+            .get_file_content(&item, "6r65pjq33banznomn7q22sj44e")
+            .await
+            .unwrap();
+        let recorded = std::fs::read_to_string(&path).unwrap();
+        assert!(!recorded.contains("00Qk3h6rX2mJ8vYbW1tN5pL9cZ4dF7gH0sA2eR"));
+        assert!(!recorded.contains("-----BEGIN-"));
+        assert!(!recorded.contains(crate::testing::MOCK_TOKEN));
+
+        let replaying = OpClient::new("http://127.0.0.1:1/v1/", "unused")
+            .unwrap()
+            .with_cassette(Cassette::replay(&path).unwrap());
+        let vault = replaying.get_vault_by_name("dev").await.unwrap();
+        let replayed = replaying
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        assert_eq!(replayed.title, item.title);
+        assert_eq!(replayed.version, item.version);
+        assert_eq!(
+            replayed
+                .get_field_by_id("credential")
+                .unwrap()
+                .expose_secret(),
+            REDACTED
+        );
+        assert!(matches!(
+            replaying.get_vault_by_id("missing").await,
+            Err(Error::NotRecorded(_))
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_coalesce_requests() {
+        let server = get_server();
+        let client = server.client();
+        server.set_latency(Duration::from_millis(100));
+        let (a, b) = futures::join!(client.get_vaults(), client.get_vaults());
+        assert_eq!(a.unwrap().len(), b.unwrap().len());
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_reference_handle_refresh() {
+        let server = get_server();
+        let client = server.client();
+        let handle = client
+            .reference_handle("op://dev/okta/credential", Duration::from_millis(20))
+            .await
+            .unwrap();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let log = calls.clone();
+        handle.on_change(move |value| {
+            log.lock().unwrap().push(value.expose_secret().to_owned());
+        });
+
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        client
+            .patch_item(
+                &item,
+                &[PatchOperation::replace_field_value(
+                    "credential",
+                    FieldValue::new("rotated"),
+                )],
+            )
+            .await
+            .unwrap();
+        for _ in 0..100 {
+            // The value is stored before the callbacks run.
+            if !calls.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(handle.load().expose_secret(), "rotated");
+        assert_eq!(*calls.lock().unwrap(), vec!["rotated".to_owned()]);
+
+        // Connect goes away: the last good value is kept.
+        for _ in 0..5 {
+            server.inject(Fault::Status(500));
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(handle.load().expose_secret(), "rotated");
+        assert_eq!(calls.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_chain_store() {
+        let server = get_server();
+        std::env::set_var("OP_CHAIN_TEST_DEV__OKTA__CREDENTIAL", "from-env");
+        // A copy of the item without values, which the chain skips as if
+        // the fields were missing.
+        let mut okta: Item =
+            serde_json::from_str(include_str!("../tests/fixtures/okta_item.json")).unwrap();
+        for field in okta.fields.iter_mut().flatten() {
+            field.value = None;
+        }
+        let blank = InMemoryStore::new()
+            .with_vault(
+                serde_json::from_str(include_str!("../tests/fixtures/dev_vault.json")).unwrap(),
+            )
+            .with_item(okta);
+        let store = ChainStore::new()
+            .with_store(EnvStore::new().with_prefix("OP_CHAIN_TEST_"))
+            .with_store(blank)
+            .with_store(server.client());
+        let value = |reference| store.get_reference(reference);
+        assert_eq!(
+            value("op://dev/okta/credential")
+                .await
+                .unwrap()
+                .expose_secret(),
+            "from-env"
+        );
+        assert_eq!(
+            value("op://dev/okta/username")
+                .await
+                .unwrap()
+                .expose_secret(),
+            "okta-admin"
+        );
+        assert!(matches!(
+            value("op://dev/stripe/key").await,
+            Err(Error::NotFound)
+        ));
+        let vaults = store.get_vaults().await.unwrap();
+        assert_eq!(vaults.len(), 1);
+        assert_eq!(store.get_items(&vaults[0]).await.unwrap().len(), 1);
     }
 }
@@ -0,0 +1,41 @@
+//! Atomic file writes.
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use rand::{rngs::OsRng, RngCore};
+
+/// Write to a temporary file in the same directory, then rename it over
+/// `path`, so readers never see a partial file.  The temporary file is
+/// created with `mode`, and given to `owner` (uid, gid) before the rename.
+/// Mode and owner are ignored on platforms other than unix.
+#[cfg_attr(not(unix), allow(unused_variables))]
+pub(crate) fn write(
+    path: &Path,
+    data: &[u8],
+    mode: u32,
+    owner: Option<(Option<u32>, Option<u32>)>,
+) -> std::io::Result<()> {
+    let tmp = path.with_extension(format!("{:016x}.tmp", OsRng.next_u64()));
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+
+    let written = options.open(&tmp).and_then(|mut file| {
+        // The umask may have cleared bits of the mode.
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(mode))?;
+        #[cfg(unix)]
+        if let Some((uid, gid)) = owner {
+            std::os::unix::fs::fchown(&file, uid, gid)?;
+        }
+        file.write_all(data)?;
+        file.sync_all()
+    });
+    let renamed = written.and_then(|()| fs::rename(&tmp, path));
+    if renamed.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    renamed
+}
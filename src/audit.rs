@@ -0,0 +1,467 @@
+//! Vault hygiene report.
+//!
+//! A [VaultAudit] looks at every item in a vault and reports reused, weak and
+//! stale passwords, Login items with URLs that are not `https`, and DATE or
+//! MONTH_YEAR fields labeled like "expires" that are close to, or past, their
+//! date.  Use `VaultAudit::run` to fetch and audit a vault in one go.
+//!
+//! Reuse is detected by comparing salted SHA-256 hashes, so no plaintext
+//! password is kept once an item has been checked, and the report itself
+//! contains no secrets.
+use std::collections::HashMap;
+use std::fmt;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use rand::{rngs::OsRng, RngCore};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::model::item::parse_date;
+use crate::{Category, FieldType, Item, MonthYear, OpClient, Result, Score, Vault};
+
+/// Audit settings.
+#[derive(Debug, Clone)]
+pub struct VaultAudit {
+    /// Items not updated for this long are reported as stale.
+    pub rotation_age: Duration,
+    /// Expiry dates this close are reported.
+    pub expiry_warning: Duration,
+    /// Passwords scoring below this are reported as weak.
+    pub min_score: Score,
+}
+
+impl Default for VaultAudit {
+    fn default() -> Self {
+        Self {
+            rotation_age: Duration::days(365),
+            expiry_warning: Duration::days(30),
+            min_score: Score::Fair,
+        }
+    }
+}
+
+/// Audit results for one vault.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub vault_id: String,
+    pub vault_name: String,
+    pub generated_at: DateTime<Utc>,
+    pub items_checked: usize,
+    pub findings: Vec<Finding>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub item_id: String,
+    pub item_title: String,
+    #[serde(flatten)]
+    pub kind: FindingKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "finding", rename_all = "snake_case")]
+pub enum FindingKind {
+    /// The password is also used by the listed items.
+    ReusedPassword {
+        shared_with: Vec<String>,
+    },
+    WeakPassword {
+        score: Score,
+        entropy: f64,
+    },
+    /// Not updated for longer than the rotation age.
+    Stale {
+        updated_at: DateTime<Utc>,
+        age_days: i64,
+    },
+    /// A Login URL that does not use https.
+    InsecureUrl {
+        href: String,
+    },
+    /// An expiry date that is near, or past when days_left is negative.
+    Expiring {
+        field: String,
+        date: NaiveDate,
+        days_left: i64,
+    },
+}
+
+impl VaultAudit {
+    pub fn with_rotation_age(self, rotation_age: Duration) -> Self {
+        Self {
+            rotation_age,
+            ..self
+        }
+    }
+
+    pub fn with_expiry_warning(self, expiry_warning: Duration) -> Self {
+        Self {
+            expiry_warning,
+            ..self
+        }
+    }
+
+    pub fn with_min_score(self, min_score: Score) -> Self {
+        Self { min_score, ..self }
+    }
+
+    /// Audit items that have already been fetched in full.
+    pub fn audit_items(&self, vault: &Vault, items: &[Item], now: DateTime<Utc>) -> AuditReport {
+        let mut auditor = self.auditor(now);
+        for item in items {
+            auditor.check(item);
+        }
+        auditor.finish(vault)
+    }
+
+    /// Fetch every item in the vault and audit them.  Each item is checked,
+    /// and dropped, before the next is fetched.
+    #[cfg(not(feature = "blocking"))]
+    pub async fn run(&self, client: &OpClient, vault: &Vault) -> Result<AuditReport> {
+        let mut auditor = self.auditor(Utc::now());
+        for overview in client.get_items(vault).await? {
+            let item = overview.fetch_detail(client).await?;
+            auditor.check(&item);
+        }
+        Ok(auditor.finish(vault))
+    }
+
+    /// Fetch every item in the vault and audit them.  Each item is checked,
+    /// and dropped, before the next is fetched.
+    #[cfg(feature = "blocking")]
+    pub fn run(&self, client: &OpClient, vault: &Vault) -> Result<AuditReport> {
+        let mut auditor = self.auditor(Utc::now());
+        for overview in client.get_items(vault)? {
+            let item = overview.fetch_detail(client)?;
+            auditor.check(&item);
+        }
+        Ok(auditor.finish(vault))
+    }
+
+    /// An audit that checks items one at a time, so that each can be dropped
+    /// once it has been checked.
+    fn auditor(&self, now: DateTime<Utc>) -> Auditor<'_> {
+        // A random salt per audit, so the hashes are useless outside of it.
+        let mut salt = [0u8; 32];
+        OsRng.fill_bytes(&mut salt);
+        Auditor {
+            settings: self,
+            now,
+            salt,
+            findings: Vec::new(),
+            reuse: HashMap::new(),
+            checked: Vec::new(),
+        }
+    }
+}
+
+/// An audit in progress.  Only password hashes, ids, titles and findings are
+/// kept between items.
+struct Auditor<'a> {
+    settings: &'a VaultAudit,
+    now: DateTime<Utc>,
+    salt: [u8; 32],
+    findings: Vec<Finding>,
+    /// Indexes into `checked` of the items with each password hash.
+    reuse: HashMap<[u8; 32], Vec<usize>>,
+    /// Id and title of every item checked.
+    checked: Vec<(String, String)>,
+}
+
+impl Auditor<'_> {
+    pub fn check(&mut self, item: &Item) {
+        let now = self.now;
+        let settings = self.settings;
+        let mut push = |kind| {
+            self.findings.push(Finding {
+                item_id: item.id.clone(),
+                item_title: item.title.clone(),
+                kind,
+            })
+        };
+
+        if let Some(strength) = item.password_strength() {
+            if strength.score < settings.min_score {
+                push(FindingKind::WeakPassword {
+                    score: strength.score,
+                    entropy: strength.entropy,
+                });
+            }
+        }
+
+        let age = now - item.updated_at;
+        if age > settings.rotation_age {
+            push(FindingKind::Stale {
+                updated_at: item.updated_at,
+                age_days: age.num_days(),
+            });
+        }
+
+        if item.category == Category::Login {
+            for url in item.urls.iter().flatten() {
+                if !url.href.trim().to_ascii_lowercase().starts_with("https://") {
+                    push(FindingKind::InsecureUrl {
+                        href: url.href.clone(),
+                    });
+                }
+            }
+        }
+
+        for (field, date) in expiry_dates(item) {
+            let days_left = (date - now.date_naive()).num_days();
+            if days_left <= settings.expiry_warning.num_days() {
+                push(FindingKind::Expiring {
+                    field,
+                    date,
+                    days_left,
+                });
+            }
+        }
+
+        if let Some(hash) = password_hash(item, &self.salt) {
+            self.reuse.entry(hash).or_default().push(self.checked.len());
+        }
+        self.checked.push((item.id.clone(), item.title.clone()));
+    }
+
+    pub fn finish(mut self, vault: &Vault) -> AuditReport {
+        let mut reused: Vec<Vec<usize>> =
+            self.reuse.into_values().filter(|i| i.len() > 1).collect();
+        reused.sort();
+        for indexes in reused {
+            for &index in &indexes {
+                let (item_id, item_title) = &self.checked[index];
+                self.findings.push(Finding {
+                    item_id: item_id.clone(),
+                    item_title: item_title.clone(),
+                    kind: FindingKind::ReusedPassword {
+                        shared_with: indexes
+                            .iter()
+                            .filter(|other| **other != index)
+                            .map(|other| self.checked[*other].0.clone())
+                            .collect(),
+                    },
+                });
+            }
+        }
+
+        AuditReport {
+            vault_id: vault.id.clone(),
+            vault_name: vault.name.clone(),
+            generated_at: self.now,
+            items_checked: self.checked.len(),
+            findings: self.findings,
+        }
+    }
+}
+
+impl AuditReport {
+    pub fn is_clean(&self) -> bool {
+        self.findings.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// Human-readable report, one line per finding.
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Vault {} ({}): {} items checked, {} findings",
+            self.vault_name,
+            self.vault_id,
+            self.items_checked,
+            self.findings.len()
+        )?;
+        for finding in &self.findings {
+            writeln!(f, "  {}", finding)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): ", self.item_title, self.item_id)?;
+        match &self.kind {
+            FindingKind::ReusedPassword { shared_with } => {
+                write!(f, "password reused by {}", shared_with.join(", "))
+            }
+            FindingKind::WeakPassword { score, entropy } => {
+                write!(f, "weak password ({:?}, {:.0} bits)", score, entropy)
+            }
+            FindingKind::Stale { age_days, .. } => {
+                write!(f, "not updated for {} days", age_days)
+            }
+            FindingKind::InsecureUrl { href } => write!(f, "insecure URL {}", href),
+            FindingKind::Expiring {
+                field,
+                date,
+                days_left,
+            } if *days_left < 0 => write!(f, "{} expired on {}", field, date),
+            FindingKind::Expiring {
+                field,
+                date,
+                days_left,
+            } => write!(f, "{} expires on {} ({} days)", field, date, days_left),
+        }
+    }
+}
+
+fn password_hash(item: &Item, salt: &[u8]) -> Option<[u8; 32]> {
+    let value = item
+        .password_field()?
+        .value
+        .as_ref()
+        .filter(|value| !value.is_empty())?;
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    hasher.update(value.inner().as_bytes());
+    Some(hasher.finalize().into())
+}
+
+/// DATE and MONTH_YEAR fields labeled like "expires", "expiry" or
+/// "expiration".
+fn expiry_dates(item: &Item) -> Vec<(String, NaiveDate)> {
+    item.fields
+        .iter()
+        .flatten()
+        .filter(|field| field.label.to_lowercase().contains("expir"))
+        .filter_map(|field| {
+            let value = field.value.as_ref()?;
+            let date = match field._type {
+                FieldType::Date => parse_date(value)?,
+                FieldType::MonthYear => value.parse::<MonthYear>().ok()?.last_day(),
+                _ => return None,
+            };
+            Some((field.label.clone(), date))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn item(id: &str, password: &str, updated_at: &str, extra: serde_json::Value) -> Item {
+        let mut json = serde_json::json!({
+            "id": id,
+            "title": format!("item {}", id),
+            "vault": {"id": "2me2svdjkmsh5xmqodspni4h6a"},
+            "category": "LOGIN",
+            "updatedAt": updated_at,
+            "fields": [
+                {"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": password}
+            ]
+        });
+        if let serde_json::Value::Object(extra) = extra {
+            json.as_object_mut().unwrap().extend(extra);
+        }
+        fixtures::item(json)
+    }
+
+    fn kinds(report: &AuditReport, id: &str) -> Vec<FindingKind> {
+        report
+            .findings
+            .iter()
+            .filter(|f| f.item_id == id)
+            .map(|f| f.kind.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_audit() {
+        let now: DateTime<Utc> = "2024-06-01T00:00:00Z".parse().unwrap();
+        let strong = "hLDegPkuMQqyQiyDZqRdWGoojiN5KYQt";
+        let items = vec![
+            item("a", strong, "2024-05-01T00:00:00Z", serde_json::json!({})),
+            item("b", strong, "2024-05-01T00:00:00Z", serde_json::json!({})),
+            item(
+                "c",
+                "letmein",
+                "2024-05-01T00:00:00Z",
+                serde_json::json!({}),
+            ),
+            item(
+                "d",
+                "Zq8!vR2#mK9@xL4$",
+                "2022-01-01T00:00:00Z",
+                serde_json::json!({}),
+            ),
+            item(
+                "e",
+                "Tb7%nW3^pQ6&yH1*",
+                "2024-05-01T00:00:00Z",
+                serde_json::json!({
+                    "urls": [
+                        {"label": "website", "primary": true, "href": "http://example.com"},
+                        {"label": "files", "href": "ftp://example.com"},
+                        {"label": "bare", "href": "example.com"},
+                        {"label": "secure", "href": "HTTPS://example.com/login"}
+                    ],
+                    "fields": [
+                        {"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": "Tb7%nW3^pQ6&yH1*"},
+                        {"id": "expires", "type": "DATE", "label": "expires", "value": "2024-06-10"},
+                        {"id": "validFrom", "type": "DATE", "label": "valid from", "value": "2024-06-10"}
+                    ]
+                }),
+            ),
+            // No password field, so the first concealed field is checked.
+            item(
+                "f",
+                strong,
+                "2024-05-01T00:00:00Z",
+                serde_json::json!({"fields": [
+                    {"id": "username", "type": "STRING", "label": "username", "value": "f"},
+                    {"id": "secret", "type": "CONCEALED", "label": "pin", "value": strong}
+                ]}),
+            ),
+        ];
+        let vault: Vault =
+            serde_json::from_str(include_str!("../tests/fixtures/dev_vault.json")).unwrap();
+        let report = VaultAudit::default().audit_items(&vault, &items, now);
+
+        assert_eq!(
+            kinds(&report, "a"),
+            vec![FindingKind::ReusedPassword {
+                shared_with: vec!["b".to_owned(), "f".to_owned()]
+            }]
+        );
+        assert!(matches!(
+            kinds(&report, "c")[..],
+            [FindingKind::WeakPassword { .. }]
+        ));
+        assert!(matches!(
+            kinds(&report, "d")[..],
+            [FindingKind::Stale { .. }]
+        ));
+        assert_eq!(
+            kinds(&report, "e"),
+            vec![
+                FindingKind::InsecureUrl {
+                    href: "http://example.com".to_owned()
+                },
+                FindingKind::InsecureUrl {
+                    href: "ftp://example.com".to_owned()
+                },
+                FindingKind::InsecureUrl {
+                    href: "example.com".to_owned()
+                },
+                FindingKind::Expiring {
+                    field: "expires".to_owned(),
+                    date: NaiveDate::from_ymd_opt(2024, 6, 10).unwrap(),
+                    days_left: 9
+                }
+            ]
+        );
+
+        let json = report.to_json().unwrap();
+        assert!(json.contains("\"finding\": \"reused_password\""));
+        assert!(!json.contains(strong));
+        assert!(!report.to_string().contains(strong));
+    }
+}
@@ -0,0 +1,132 @@
+//! Serve secrets to local processes over a Unix socket.  See the
+//! `op_connect_rs::agent` module.
+#![cfg_attr(feature = "blocking", allow(dead_code))]
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: op-agent --socket PATH --policy FILE [--cache-ttl SECONDS] [--socket-mode MODE]
+
+Serves secret references to local processes, one JSON line per request:
+    {\"reference\": \"op://vault/item/field\"}
+
+  --socket PATH        Unix socket to listen on
+  --policy FILE        JSON allowlist of callers and references
+  --cache-ttl SECONDS  how long to cache responses, default 60
+  --socket-mode MODE   octal permissions of the socket, default 660
+
+The Connect server is read from OP_PATH, default http://localhost:8080/v1/,
+and the token from OP_API_TOKEN.  Every request is logged to stderr.";
+
+struct Args {
+    socket: PathBuf,
+    policy: PathBuf,
+    cache_ttl: Duration,
+    socket_mode: u32,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut socket = None;
+    let mut policy = None;
+    let mut cache_ttl = Duration::from_secs(60);
+    let mut socket_mode = 0o660;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--socket" => socket = Some(PathBuf::from(value()?)),
+            "--policy" => policy = Some(PathBuf::from(value()?)),
+            "--cache-ttl" => {
+                let seconds = value()?;
+                let seconds = seconds
+                    .parse()
+                    .map_err(|_| format!("invalid --cache-ttl {}", seconds))?;
+                cache_ttl = Duration::from_secs(seconds);
+            }
+            "--socket-mode" => {
+                let mode = value()?;
+                socket_mode = u32::from_str_radix(&mode, 8)
+                    .map_err(|_| format!("invalid --socket-mode {}", mode))?;
+            }
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+    Ok(Args {
+        socket: socket.ok_or("--socket is required")?,
+        policy: policy.ok_or("--policy is required")?,
+        cache_ttl,
+        socket_mode,
+    })
+}
+
+/// Logs info and above to stderr.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Info
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!(
+                "{} {} {}: {}",
+                chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(not(feature = "blocking"))]
+#[tokio::main]
+async fn main() -> ExitCode {
+    use op_connect_rs::agent::{Agent, AgentPolicy};
+    use op_connect_rs::OpClient;
+
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("op-agent: {}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let _ = log::set_logger(&StderrLogger).map(|()| log::set_max_level(log::LevelFilter::Info));
+
+    let path = std::env::var("OP_PATH").unwrap_or("http://localhost:8080/v1/".to_owned());
+    let served = async {
+        let token = std::env::var("OP_API_TOKEN").map_err(|_| op_connect_rs::Error::TokenNotSet)?;
+        let token = zeroize::Zeroizing::new(token);
+        let policy = AgentPolicy::load(&args.policy)?;
+        let client = OpClient::new(&path, &token)?.with_cache(args.cache_ttl);
+        log::info!("listening on {}", args.socket.display());
+        Agent::new(client, policy)
+            .with_socket_mode(args.socket_mode)
+            .serve(&args.socket)
+            .await
+    };
+    match served.await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            log::error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// The agent serves with the async client, which the `blocking` feature
+/// replaces.
+#[cfg(feature = "blocking")]
+fn main() -> ExitCode {
+    eprintln!("op-agent is not available with the blocking feature");
+    ExitCode::FAILURE
+}
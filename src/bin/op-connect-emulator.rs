@@ -0,0 +1,107 @@
+//! Serve the Connect v1 API from a data directory, for local development.
+//! See the `op_connect_rs::emulator` module.
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use op_connect_rs::emulator::DataDir;
+use op_connect_rs::testing::{MockServer, MOCK_TOKEN};
+
+const USAGE: &str = "\
+usage: op-connect-emulator --data DIR [--listen ADDR] [--token TOKEN]
+
+Serves the Connect v1 API from DIR, which holds one JSON or YAML document per
+vault, and files/<file id> for attachments.  Writes are saved back to DIR.
+
+  --data DIR     data directory
+  --listen ADDR  address to listen on, default 127.0.0.1:8080
+  --token TOKEN  bearer token to accept, default OP_API_TOKEN, or
+                 mock-connect-token if that is not set
+
+Point clients at http://ADDR/v1/, for example with OP_PATH.";
+
+struct Args {
+    data: PathBuf,
+    listen: String,
+    token: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut data = None;
+    let mut listen = "127.0.0.1:8080".to_owned();
+    let mut token = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--data" => data = Some(PathBuf::from(value()?)),
+            "--listen" => listen = value()?,
+            "--token" => token = Some(value()?),
+            "-h" | "--help" => return Err(String::new()),
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+    Ok(Args {
+        data: data.ok_or("--data is required")?,
+        listen,
+        token,
+    })
+}
+
+/// Logs warnings and errors, such as failed saves, to stderr.
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::Level::Warn
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}: {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(message) => {
+            if !message.is_empty() {
+                eprintln!("op-connect-emulator: {}\n", message);
+            }
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let _ = log::set_logger(&StderrLogger).map(|()| log::set_max_level(log::LevelFilter::Warn));
+    let data = match DataDir::load(&args.data) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("op-connect-emulator: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let server = match MockServer::bind(&args.listen) {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!(
+                "op-connect-emulator: cannot listen on {}: {}",
+                args.listen, e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    server.set_recording(false);
+    match args.token.or_else(|| std::env::var("OP_API_TOKEN").ok()) {
+        Some(token) => server.set_token(&token),
+        None => eprintln!("accepting the token {}", MOCK_TOKEN),
+    }
+    let vaults = data.vaults().count();
+    data.serve(&server);
+    eprintln!("serving {} vaults on {}", vaults, server.url());
+    loop {
+        std::thread::park();
+    }
+}
@@ -0,0 +1,335 @@
+//! In-memory response cache.
+//!
+//! Enabled with `OpClient::with_cache`.  Vault lists, vaults, item lists and
+//! item details are kept for a TTL.  Once an item list or item detail is
+//! older than the TTL it is not thrown away: the vault's `content_version`
+//! is fetched, which is a single small request, and if it has not changed
+//! since the entry was stored the entry is trusted for another TTL.
+//!
+//! The vault is always fetched from Connect for this, never from the cache.
+//!
+//! An expired entry is dropped when revalidation fails, and entries that
+//! have not been revalidated within twice the TTL are swept out whenever a
+//! new entry is stored.  Field values zeroize themselves on drop, and inline
+//! file contents are wiped when an item is evicted.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use zeroize::Zeroize;
+
+use crate::{Item, ItemOverview, Vault};
+
+/// Cache hit and miss counts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Requests answered from the cache, including revalidated entries.
+    pub hits: u64,
+    /// Requests that went to Connect.
+    pub misses: u64,
+    /// Expired entries that were kept because the vault had not changed.
+    pub revalidations: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    revalidations: AtomicU64,
+}
+
+/// Wipes secrets held by a cached value when it is evicted.
+pub(crate) trait Evict {
+    fn evict(&mut self) {}
+}
+
+impl Evict for Vault {}
+impl Evict for Vec<Vault> {}
+impl Evict for Vec<ItemOverview> {}
+
+impl Evict for Item {
+    fn evict(&mut self) {
+        for file in self.files.iter_mut().flatten() {
+            file.content.zeroize();
+        }
+    }
+}
+
+/// The current time.  Tests substitute a clock they can advance.
+type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+pub(crate) struct Cache {
+    pub vaults: Table<Vec<Vault>>,
+    pub vault: Table<Vault>,
+    pub items: Table<Vec<ItemOverview>>,
+    pub details: Table<Item>,
+    counters: Arc<Counters>,
+}
+
+impl Cache {
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, Arc::new(Instant::now))
+    }
+
+    fn with_clock(ttl: Duration, clock: Clock) -> Self {
+        let counters = Arc::new(Counters::default());
+        Self {
+            vaults: Table::new(ttl, counters.clone(), clock.clone()),
+            vault: Table::new(ttl, counters.clone(), clock.clone()),
+            items: Table::new(ttl, counters.clone(), clock.clone()),
+            details: Table::new(ttl, counters.clone(), clock),
+            counters,
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.counters.hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            revalidations: self.counters.revalidations.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn clear(&self) {
+        self.vaults.clear();
+        self.vault.clear();
+        self.items.clear();
+        self.details.clear();
+    }
+
+    /// Forget an item, and the item list of its vault, after it was changed.
+    pub fn invalidate_item(&self, vault_id: &str, item_id: &str) {
+        self.items.remove(vault_id);
+        self.details.remove(&detail_key(vault_id, item_id));
+    }
+}
+
+pub(crate) fn detail_key(vault_id: &str, item_id: &str) -> String {
+    format!("{}/{}", vault_id, item_id)
+}
+
+pub(crate) enum Lookup<V> {
+    Fresh(V),
+    /// Older than the TTL, and must be revalidated before use.
+    Expired,
+    Miss,
+}
+
+struct Entry<V: Evict> {
+    value: V,
+    /// The vault's content version when the entry was stored.
+    content_version: Option<u32>,
+    stored_at: Instant,
+}
+
+impl<V: Evict> Drop for Entry<V> {
+    fn drop(&mut self) {
+        self.value.evict();
+    }
+}
+
+pub(crate) struct Table<V: Evict> {
+    ttl: Duration,
+    counters: Arc<Counters>,
+    clock: Clock,
+    entries: Mutex<HashMap<String, Entry<V>>>,
+}
+
+impl<V: Evict + Clone> Table<V> {
+    fn new(ttl: Duration, counters: Arc<Counters>, clock: Clock) -> Self {
+        Self {
+            ttl,
+            counters,
+            clock,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Time since `stored_at`.
+    fn age(&self, stored_at: Instant) -> Duration {
+        (self.clock)().saturating_duration_since(stored_at)
+    }
+
+    fn entries(&self) -> MutexGuard<'_, HashMap<String, Entry<V>>> {
+        // The map is never left half-updated, so a poisoned lock is safe to use.
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Look up an entry that is only good for the TTL.  An expired entry
+    /// is dropped.
+    pub fn get(&self, key: &str) -> Option<V> {
+        match self.lookup(key) {
+            Lookup::Fresh(value) => Some(value),
+            Lookup::Expired => {
+                self.remove(key);
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+            Lookup::Miss => None,
+        }
+    }
+
+    /// Look up an entry.  An expired entry is counted once it has been
+    /// revalidated or replaced.  One past its retention is dropped.
+    pub fn lookup(&self, key: &str) -> Lookup<V> {
+        let mut entries = self.entries();
+        match entries.get(key) {
+            Some(entry) if self.age(entry.stored_at) < self.ttl => {
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                Lookup::Fresh(entry.value.clone())
+            }
+            Some(entry) if self.age(entry.stored_at) < self.retention() => Lookup::Expired,
+            entry => {
+                if entry.is_some() {
+                    entries.remove(key);
+                }
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                Lookup::Miss
+            }
+        }
+    }
+
+    /// How long an expired entry is kept for revalidation.
+    fn retention(&self) -> Duration {
+        self.ttl.saturating_mul(2)
+    }
+
+    /// Renew an expired entry if the vault's content version still matches.
+    pub fn revalidate(&self, key: &str, content_version: Option<u32>) -> Option<V> {
+        let mut entries = self.entries();
+        match entries.get_mut(key) {
+            Some(entry)
+                if content_version.is_some() && entry.content_version == content_version =>
+            {
+                entry.stored_at = (self.clock)();
+                self.counters.hits.fetch_add(1, Ordering::Relaxed);
+                self.counters.revalidations.fetch_add(1, Ordering::Relaxed);
+                Some(entry.value.clone())
+            }
+            entry => {
+                if entry.is_some() {
+                    entries.remove(key);
+                }
+                self.counters.misses.fetch_add(1, Ordering::Relaxed);
+                None
+            }
+        }
+    }
+
+    /// Store a value.  The content version must have been read before the
+    /// value was fetched, so that a concurrent change makes it look older,
+    /// never newer.
+    pub fn insert(&self, key: String, content_version: Option<u32>, value: V) {
+        let retention = self.retention();
+        let mut entries = self.entries();
+        entries.retain(|_, entry| self.age(entry.stored_at) < retention);
+        entries.insert(
+            key,
+            Entry {
+                value,
+                content_version,
+                stored_at: (self.clock)(),
+            },
+        );
+    }
+
+    pub fn remove(&self, key: &str) {
+        self.entries().remove(key);
+    }
+
+    fn clear(&self) {
+        self.entries().clear();
+    }
+
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.entries().len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A clock that only moves when advanced, and the time it reads.
+    fn manual_clock() -> (Clock, Arc<Mutex<Instant>>) {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let read = now.clone();
+        (Arc::new(move || *read.lock().unwrap()), now)
+    }
+
+    fn advance(now: &Mutex<Instant>, by: Duration) {
+        *now.lock().unwrap() += by;
+    }
+
+    #[test]
+    fn test_lookup_and_revalidate() {
+        let cache = Cache::new(Duration::from_secs(60));
+        assert!(matches!(cache.vault.lookup("a"), Lookup::Miss));
+        cache.vaults.insert(String::new(), None, Vec::new());
+        assert!(cache.vaults.get("").is_some());
+        assert_eq!(
+            cache.stats(),
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                revalidations: 0
+            }
+        );
+
+        let (clock, now) = manual_clock();
+        let expired = Cache::with_clock(Duration::from_secs(60), clock);
+        expired.items.insert("v".to_owned(), Some(7), Vec::new());
+        advance(&now, Duration::from_secs(59));
+        assert!(matches!(expired.items.lookup("v"), Lookup::Fresh(_)));
+        advance(&now, Duration::from_secs(1));
+        assert!(matches!(expired.items.lookup("v"), Lookup::Expired));
+        assert!(expired.items.revalidate("v", Some(7)).is_some());
+        advance(&now, Duration::from_secs(60));
+        assert!(expired.items.revalidate("v", Some(8)).is_none());
+        // A failed revalidation drops the entry.
+        assert!(expired.items.revalidate("v", Some(8)).is_none());
+        assert_eq!(expired.items.len(), 0);
+        assert_eq!(
+            expired.stats(),
+            CacheStats {
+                hits: 2,
+                misses: 2,
+                revalidations: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_sweep() {
+        let (clock, now) = manual_clock();
+        let cache = Cache::with_clock(Duration::from_secs(60), clock);
+        cache.details.insert("v/a".to_owned(), Some(1), item());
+        cache.vault.insert("v".to_owned(), None, vault());
+        advance(&now, Duration::from_secs(60));
+        assert!(matches!(cache.details.lookup("v/a"), Lookup::Expired));
+        assert!(cache.vault.get("v").is_none());
+        assert_eq!(cache.vault.len(), 0);
+        advance(&now, Duration::from_secs(60));
+        cache.details.insert("v/b".to_owned(), Some(1), item());
+        assert_eq!(cache.details.len(), 1);
+        assert!(matches!(cache.details.lookup("v/a"), Lookup::Miss));
+    }
+
+    fn vault() -> Vault {
+        serde_json::from_str(include_str!("../tests/fixtures/dev_vault.json")).unwrap()
+    }
+
+    fn item() -> Item {
+        serde_json::from_str(include_str!("../tests/fixtures/okta_item.json")).unwrap()
+    }
+
+    #[test]
+    fn test_invalidate_item() {
+        let cache = Cache::new(Duration::from_secs(60));
+        cache.items.insert("v".to_owned(), Some(1), Vec::new());
+        cache.invalidate_item("v", "i");
+        assert!(cache.items.get("v").is_none());
+    }
+}
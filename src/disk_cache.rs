@@ -0,0 +1,342 @@
+//! Encrypted on-disk cache, so services can start from the last known
+//! secrets while Connect is unreachable.
+//!
+//! Enabled with `OpClient::with_disk_cache`.  Every successful response is
+//! written to its own file, encrypted with XChaCha20-Poly1305 under a key
+//! supplied by the application, for example one derived from a machine
+//! secret.  The request path is bound to the ciphertext as associated data,
+//! so files cannot be swapped.  Files are written atomically with mode 0600.
+//!
+//! When a request fails because Connect cannot be reached, the last stored
+//! response is returned instead, marked [Freshness::Stale].  Entries expire
+//! `max_age` after they were stored, after which they are deleted rather than
+//! used.
+use std::fs;
+use std::io::ErrorKind;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use chrono::{DateTime, Utc};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+
+use crate::atomic_file;
+use crate::{Error, Result};
+
+const NONCE_LEN: usize = 24;
+
+/// Whether a result came from Connect or from the disk cache.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freshness {
+    /// Fetched from Connect, or from the in-memory cache.
+    Fresh,
+    /// Connect was unreachable, and this is the copy stored at `stored_at`.
+    Stale { stored_at: DateTime<Utc> },
+}
+
+/// A result and where it came from.
+#[derive(Debug, Clone)]
+pub struct Fetched<T> {
+    pub value: T,
+    pub freshness: Freshness,
+}
+
+impl<T> Fetched<T> {
+    pub(crate) fn fresh(value: T) -> Self {
+        Self {
+            value,
+            freshness: Freshness::Fresh,
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.freshness != Freshness::Fresh
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> Deref for Fetched<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+/// Encrypted response store.
+pub struct DiskCache {
+    dir: PathBuf,
+    cipher: XChaCha20Poly1305,
+    max_age: Duration,
+}
+
+impl std::fmt::Debug for DiskCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DiskCache")
+            .field("dir", &self.dir)
+            .field("max_age", &self.max_age)
+            .finish()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredEntry<T> {
+    stored_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    value: T,
+}
+
+impl DiskCache {
+    /// Store entries in `dir`, which is created with mode 0700 if needed.
+    /// Entries are kept for 7 days by default.
+    pub fn new(dir: impl Into<PathBuf>, key: &[u8; 32]) -> Result<Self> {
+        let dir = dir.into();
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(&dir)?;
+        Ok(Self {
+            dir,
+            cipher: XChaCha20Poly1305::new(key.into()),
+            max_age: Duration::from_secs(7 * 24 * 60 * 60),
+        })
+    }
+
+    pub fn with_max_age(self, max_age: Duration) -> Self {
+        Self { max_age, ..self }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Encrypt and store a value under `key`, replacing any earlier entry.
+    pub fn store<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let stored_at = Utc::now();
+        let expires_at = chrono::Duration::from_std(self.max_age)
+            .ok()
+            .and_then(|max_age| stored_at.checked_add_signed(max_age))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        let entry = StoredEntry {
+            stored_at,
+            expires_at,
+            value,
+        };
+        let plaintext = Zeroizing::new(serde_json::to_vec(&entry)?);
+
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: key.as_bytes(),
+                },
+            )
+            .map_err(|_e| Error::Encryption)?;
+
+        let mut data = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+        Ok(atomic_file::write(&self.path(key), &data, 0o600, None)?)
+    }
+
+    /// Load the value stored under `key`, if there is one that has not
+    /// expired.  Entries that cannot be decrypted, for example because the key
+    /// changed, are treated as missing.
+    pub fn load<T: for<'a> Deserialize<'a>>(&self, key: &str) -> Result<Option<Fetched<T>>> {
+        let path = self.path(key);
+        let data = match fs::read(&path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if data.len() < NONCE_LEN {
+            log::warn!("ignoring truncated cache entry {}", path.display());
+            return Ok(None);
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let plaintext = match self.cipher.decrypt(
+            XNonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: key.as_bytes(),
+            },
+        ) {
+            Ok(plaintext) => Zeroizing::new(plaintext),
+            Err(_) => {
+                log::warn!(
+                    "ignoring cache entry {} that failed to decrypt",
+                    path.display()
+                );
+                return Ok(None);
+            }
+        };
+        let entry = serde_json::from_slice::<StoredEntry<T>>(&plaintext)?;
+        if entry.expires_at <= Utc::now() {
+            self.remove(key)?;
+            return Ok(None);
+        }
+        Ok(Some(Fetched {
+            value: entry.value,
+            freshness: Freshness::Stale {
+                stored_at: entry.stored_at,
+            },
+        }))
+    }
+
+    pub fn remove(&self, key: &str) -> Result<()> {
+        match fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// File names are hashes of the key, so they do not reveal vault or item
+    /// ids.
+    fn path(&self, key: &str) -> PathBuf {
+        let hash = Sha256::digest(key.as_bytes());
+        let name: String = hash.iter().map(|b| format!("{:02x}", b)).collect();
+        self.dir.join(name)
+    }
+}
+
+/// Store a successful response, or fall back to the stored copy when Connect
+/// is unreachable.
+// The async client with `async` runs the I/O on the blocking pool instead.
+#[cfg(any(test, feature = "blocking", not(feature = "async")))]
+pub(crate) fn persisted<T>(
+    disk: Option<&DiskCache>,
+    key: &str,
+    result: Result<T>,
+) -> Result<Fetched<T>>
+where
+    T: Serialize + for<'a> Deserialize<'a>,
+{
+    let Some(disk) = disk else {
+        return result.map(Fetched::fresh);
+    };
+    match result {
+        Ok(value) => {
+            stored(disk.store(key, &value));
+            Ok(Fetched::fresh(value))
+        }
+        Err(e) if e.is_unavailable() => fallback(key, e, disk.load::<T>(key)),
+        Err(e) => Err(e),
+    }
+}
+
+/// Log a failure to store a response.
+pub(crate) fn stored(result: Result<()>) {
+    if let Err(e) = result {
+        log::warn!("failed to write disk cache: {}", e);
+    }
+}
+
+/// The stored copy of a response, if there is one, in place of `error`.
+pub(crate) fn fallback<T>(
+    key: &str,
+    error: Error,
+    loaded: Result<Option<Fetched<T>>>,
+) -> Result<Fetched<T>> {
+    match loaded {
+        Ok(Some(fetched)) => {
+            log::warn!("Connect is unavailable, using cached {}: {}", key, error);
+            Ok(fetched)
+        }
+        Ok(None) => Err(error),
+        Err(load) => {
+            log::warn!("failed to read disk cache: {}", load);
+            Err(error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "op_connect_rs-{}-{}-{:x}",
+            name,
+            std::process::id(),
+            OsRng.next_u32()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let dir = temp_dir("round-trip");
+        let cache = DiskCache::new(&dir, &[7; 32]).unwrap();
+        cache
+            .store("vaults/v/items/i", &"hunter2".to_owned())
+            .unwrap();
+
+        let fetched = cache.load::<String>("vaults/v/items/i").unwrap().unwrap();
+        assert_eq!(fetched.value, "hunter2");
+        assert!(fetched.is_stale());
+        assert!(cache.load::<String>("vaults/v/items/j").unwrap().is_none());
+
+        let path = cache.path("vaults/v/items/i");
+        assert!(!fs::read(&path).unwrap().windows(7).any(|w| w == b"hunter2"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(
+                fs::metadata(&path).unwrap().permissions().mode() & 0o777,
+                0o600
+            );
+        }
+
+        // A different key, or an entry moved to another key, does not decrypt.
+        let other = DiskCache::new(&dir, &[8; 32]).unwrap();
+        assert!(other.load::<String>("vaults/v/items/i").unwrap().is_none());
+        fs::copy(&path, cache.path("vaults/v/items/j")).unwrap();
+        assert!(cache.load::<String>("vaults/v/items/j").unwrap().is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expired() {
+        let dir = temp_dir("expired");
+        let cache = DiskCache::new(&dir, &[7; 32])
+            .unwrap()
+            .with_max_age(Duration::ZERO);
+        cache.store("vaults", &Vec::<String>::new()).unwrap();
+        assert!(cache.load::<Vec<String>>("vaults").unwrap().is_none());
+        assert!(!cache.path("vaults").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_fallback() {
+        let dir = temp_dir("fallback");
+        let cache = DiskCache::new(&dir, &[7; 32]).unwrap();
+        let unavailable = || Error::Api {
+            status: 503,
+            message: String::new(),
+        };
+
+        assert!(persisted::<u32>(Some(&cache), "k", Err(unavailable())).is_err());
+        assert!(!persisted(Some(&cache), "k", Ok(1u32)).unwrap().is_stale());
+        let fetched = persisted::<u32>(Some(&cache), "k", Err(unavailable())).unwrap();
+        assert_eq!(*fetched, 1);
+        assert!(fetched.is_stale());
+        assert!(persisted::<u32>(Some(&cache), "k", Err(Error::NotFound)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
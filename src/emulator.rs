@@ -0,0 +1,402 @@
+//! A Connect server for local development, backed by a data directory.
+//!
+//! The emulator serves the Connect v1 API with a [MockServer], seeded from a
+//! directory holding one document per vault, in JSON (`*.json`) or YAML
+//! (`*.yaml`, `*.yml`):
+//! ```yaml
+//! vault:
+//!   id: 2me2svdjkmsh5xmqodspni4h6a
+//!   name: dev
+//! items:
+//!   - id: xo3ezyf5tlcp6lilvb6yfwbo3a
+//!     title: Postgres
+//!     category: DATABASE
+//!     fields:
+//!       - {id: password, type: CONCEALED, label: password, value: hunter2}
+//! ```
+//! Items may leave out `vault`, `version`, the timestamps and
+//! `lastEditedBy`, which are filled in.  Vault and item ids must be unique
+//! across the directory.  The content of file attachments is read from
+//! `files/<file id>`.
+//!
+//! Writes through the API bump versions like a real server, and the vault's
+//! document is rewritten in its own format.  Comments and formatting in
+//! hand-written documents are not kept.
+//!
+//! The `op-connect-emulator` binary runs an emulator; see its `--help`.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::testing::MockServer;
+use crate::{atomic_file, Error, Item, Result, Vault};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Json,
+    Yaml,
+}
+
+impl Format {
+    fn of(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Format::Json),
+            "yaml" | "yml" => Some(Format::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// A vault and its items, as stored in the data directory.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VaultDocument {
+    pub vault: Vault,
+    #[serde(default)]
+    pub items: Vec<Item>,
+}
+
+/// The documents in a data directory, and where each came from.
+#[derive(Debug)]
+pub struct DataDir {
+    dir: PathBuf,
+    documents: Vec<(PathBuf, VaultDocument)>,
+    /// File contents, by file id.
+    files: HashMap<String, Vec<u8>>,
+}
+
+impl DataDir {
+    pub fn load(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<_>>()?;
+        paths.sort();
+        let mut documents = Vec::new();
+        for path in paths {
+            let Some(format) = Format::of(&path) else {
+                continue;
+            };
+            let document = read_document(&path, format)
+                .map_err(|e| Error::InvalidDataFile(format!("{}: {}", path.display(), e)))?;
+            documents.push((path, document));
+        }
+        check_unique(&documents)?;
+
+        let mut files = HashMap::new();
+        match fs::read_dir(dir.join("files")) {
+            Ok(entries) => {
+                for entry in entries {
+                    let path = entry?.path();
+                    if let Some(file_id) = path.file_name().and_then(|name| name.to_str()) {
+                        files.insert(file_id.to_owned(), fs::read(&path)?);
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+        Ok(Self {
+            dir: dir.to_owned(),
+            documents,
+            files,
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn vaults(&self) -> impl Iterator<Item = &VaultDocument> {
+        self.documents.iter().map(|(_, document)| document)
+    }
+
+    /// Seed `server` with the vaults, items and files, and rewrite a vault's
+    /// document whenever it is written through the API.
+    pub fn serve(self, server: &MockServer) {
+        let mut paths = HashMap::new();
+        for (path, document) in self.documents {
+            server.add_vault(document.vault.clone());
+            for item in document.items {
+                server.add_item(item);
+            }
+            paths.insert(document.vault.id, path);
+        }
+        for (file_id, content) in &self.files {
+            server.add_file_content(file_id, content);
+        }
+        server.on_write(move |vault, items| {
+            let Some(path) = paths.get(&vault.id) else {
+                return;
+            };
+            if let Err(e) = write_document(path, vault, items) {
+                log::error!("failed to save {}: {}", path.display(), e);
+            }
+        });
+    }
+}
+
+/// The server keeps vaults and items by id, so a second copy would replace
+/// the first.
+fn check_unique(documents: &[(PathBuf, VaultDocument)]) -> Result<()> {
+    let mut seen = HashMap::new();
+    for (path, document) in documents {
+        let ids = std::iter::once(("vault", &document.vault.id))
+            .chain(document.items.iter().map(|item| ("item", &item.id)));
+        for (kind, id) in ids {
+            if let Some(first) = seen.insert((kind, id), path) {
+                return Err(Error::InvalidDataFile(format!(
+                    "{}: {} {} is also in {}",
+                    path.display(),
+                    kind,
+                    id,
+                    first.display()
+                )));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_document(path: &Path, format: Format) -> Result<VaultDocument> {
+    let text = fs::read_to_string(path)?;
+    let mut value: Value = match format {
+        Format::Json => serde_json::from_str(&text)?,
+        Format::Yaml => {
+            serde_yaml::from_str(&text).map_err(|e| Error::InvalidDataFile(e.to_string()))?
+        }
+    };
+    let vault_id = value["vault"]["id"].clone();
+    if let Some(items) = value.get_mut("items").and_then(Value::as_array_mut) {
+        for item in items {
+            fill_defaults(item, &vault_id)?;
+        }
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Fill in the item attributes a server would set.  An item that names
+/// another vault than its document's is rejected: it would be served from
+/// one vault and saved to the other.
+fn fill_defaults(item: &mut Value, vault_id: &Value) -> Result<()> {
+    let Some(item) = item.as_object_mut() else {
+        return Ok(());
+    };
+    if let Some(id) = item.get("vault").and_then(|vault| vault.get("id")) {
+        if id != vault_id {
+            return Err(Error::InvalidDataFile(format!(
+                "item {} is in vault {}, not {}",
+                item.get("id").unwrap_or(&Value::Null),
+                id,
+                vault_id
+            )));
+        }
+    }
+    let now = json!(Utc::now());
+    item.entry("vault")
+        .or_insert_with(|| json!({"id": vault_id}));
+    item.entry("version").or_insert(json!(1));
+    item.entry("createdAt").or_insert_with(|| now.clone());
+    item.entry("updatedAt").or_insert(now);
+    item.entry("lastEditedBy").or_insert(json!("EMULATOR"));
+    Ok(())
+}
+
+fn write_document(path: &Path, vault: &Vault, items: &[&Item]) -> Result<()> {
+    #[derive(Serialize)]
+    struct Document<'a> {
+        vault: &'a Vault,
+        items: &'a [&'a Item],
+    }
+    let document = Document { vault, items };
+    let data = match Format::of(path) {
+        Some(Format::Yaml) => serde_yaml::to_string(&document)
+            .map_err(|e| Error::InvalidDataFile(e.to_string()))?
+            .into_bytes(),
+        _ => serde_json::to_vec_pretty(&document)?,
+    };
+    Ok(atomic_file::write(path, &data, 0o600, None)?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{FieldValue, PatchOperation};
+
+    #[test]
+    fn test_load_and_save() {
+        let dir =
+            std::env::temp_dir().join(format!("op_connect_rs-emulator-{}", std::process::id()));
+        fs::create_dir_all(dir.join("files")).unwrap();
+        fs::write(
+            dir.join("dev.yaml"),
+            "vault:\n  id: v1\n  name: dev\nitems:\n  - id: i1\n    title: db\n    category: DATABASE\n    fields:\n      - {id: password, type: CONCEALED, label: password, value: hunter2}\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("prod.json"),
+            r#"{"vault": {"id": "v2", "name": "prod"}}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("files").join("f1"), b"content").unwrap();
+        fs::write(dir.join("README"), b"ignored").unwrap();
+
+        let data = DataDir::load(&dir).unwrap();
+        let vaults: Vec<&str> = data.vaults().map(|d| d.vault.name.as_str()).collect();
+        assert_eq!(vaults, vec!["dev", "prod"]);
+        let item = &data.vaults().next().unwrap().items[0];
+        assert_eq!(item.vault.id, "v1");
+        assert_eq!(item.version, 1);
+        assert_eq!(data.files["f1"], b"content");
+
+        let item = item.clone();
+        let written = dir.join("dev.yaml");
+        write_document(&written, &data.vaults().next().unwrap().vault, &[&item]).unwrap();
+        let reloaded = DataDir::load(&dir).unwrap();
+        let field = reloaded.vaults().next().unwrap().items[0].get_field_by_id("password");
+        assert_eq!(field.unwrap().expose_secret(), "hunter2");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        let dir = std::env::temp_dir().join(format!(
+            "op_connect_rs-emulator-duplicates-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let item = r#"{"id": "i1", "title": "db", "category": "DATABASE"}"#;
+        fs::write(
+            dir.join("dev.json"),
+            format!(
+                r#"{{"vault": {{"id": "v1", "name": "dev"}}, "items": [{}]}}"#,
+                item
+            ),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("prod.json"),
+            format!(
+                r#"{{"vault": {{"id": "v2", "name": "prod"}}, "items": [{}]}}"#,
+                item
+            ),
+        )
+        .unwrap();
+        assert!(matches!(
+            DataDir::load(&dir),
+            Err(Error::InvalidDataFile(message)) if message.contains("item i1 is also in")
+        ));
+
+        fs::write(
+            dir.join("prod.json"),
+            r#"{"vault": {"id": "v1", "name": "prod"}}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            DataDir::load(&dir),
+            Err(Error::InvalidDataFile(message)) if message.contains("vault v1 is also in")
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_item_in_other_vault() {
+        let dir = std::env::temp_dir().join(format!(
+            "op_connect_rs-emulator-other-vault-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("dev.json"),
+            r#"{"vault": {"id": "v1", "name": "dev"}, "items": [{"id": "i1", "title": "db", "category": "DATABASE", "vault": {"id": "v1"}}]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            DataDir::load(&dir).unwrap().vaults().next().unwrap().items[0]
+                .vault
+                .id,
+            "v1"
+        );
+
+        fs::write(
+            dir.join("dev.json"),
+            r#"{"vault": {"id": "v1", "name": "dev"}, "items": [{"id": "i1", "title": "db", "category": "DATABASE", "vault": {"id": "v2"}}]}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            DataDir::load(&dir),
+            Err(Error::InvalidDataFile(message)) if message.contains(r#"item "i1" is in vault "v2", not "v1""#)
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A data directory holding the dev vault and the okta item, and a
+    /// server seeded from it.
+    fn serve_okta(name: &str) -> (PathBuf, MockServer) {
+        let dir = std::env::temp_dir().join(format!(
+            "op_connect_rs-emulator-{}-{}",
+            name,
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let document = VaultDocument {
+            vault: serde_json::from_str(include_str!("../tests/fixtures/dev_vault.json")).unwrap(),
+            items: vec![
+                serde_json::from_str(include_str!("../tests/fixtures/okta_item.json")).unwrap(),
+            ],
+        };
+        fs::write(dir.join("dev.json"), serde_json::to_vec(&document).unwrap()).unwrap();
+        let server = MockServer::start();
+        DataDir::load(&dir).unwrap().serve(&server);
+        (dir, server)
+    }
+
+    /// The okta item as saved after a write through the API.
+    fn check_saved(dir: &Path, version: u32) {
+        let reloaded = DataDir::load(dir).unwrap();
+        let item = &reloaded.vaults().next().unwrap().items[0];
+        assert_eq!(item.version, version);
+        assert_eq!(
+            item.get_field_by_id("credential").unwrap().expose_secret(),
+            "rotated"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    #[tokio::test]
+    async fn test_write_through_api() {
+        let (dir, server) = serve_okta("async");
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").await.unwrap();
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .await
+            .unwrap();
+        let operations = [PatchOperation::replace_field_value(
+            "credential",
+            FieldValue::new("rotated"),
+        )];
+        client.patch_item(&item, &operations).await.unwrap();
+        check_saved(&dir, item.version + 1);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_write_through_api() {
+        let (dir, server) = serve_okta("blocking");
+        let client = server.client();
+        let vault = client.get_vault_by_name("dev").unwrap();
+        let item = client
+            .get_item_detail(&vault, "xo3ezyf5tlcp6lilvb6yfwbo3a")
+            .unwrap();
+        let operations = [PatchOperation::replace_field_value(
+            "credential",
+            FieldValue::new("rotated"),
+        )];
+        client.patch_item(&item, &operations).unwrap();
+        check_saved(&dir, item.version + 1);
+    }
+}
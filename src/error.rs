@@ -1,11 +1,16 @@
+use serde::Deserialize;
 use thiserror::Error;
 
+use crate::{Category, FieldValue, HookError, Rotation};
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("OP_API_TOKEN was not set in the env")]
     TokenNotSet,
+    #[error("OP_API_TOKEN contains invalid characters")]
+    InvalidToken,
     #[error("Url parse error")]
     UrlParse,
     #[error("Reqwest error")]
@@ -14,4 +19,78 @@ pub enum Error {
     SerdeJson(#[from] serde_json::Error),
     #[error("Not Found")]
     NotFound,
+    #[error("Connect returned {status}: {message}")]
+    Api { status: u16, message: String },
+    #[error("Expected a {expected:?} item, found {found:?}")]
+    CategoryMismatch { expected: Category, found: Category },
+    #[error("Required field {0} is missing")]
+    MissingField(String),
+    #[error("Field {0} has an invalid value")]
+    InvalidField(String),
+    #[error("More than one field is labeled {0}")]
+    AmbiguousField(String),
+    #[error("Invalid secret reference {0}")]
+    InvalidReference(String),
+    #[error("Invalid generator recipe: {0}")]
+    InvalidRecipe(String),
+    #[error("Item changed since it was read: expected version {expected}, found {found}")]
+    VersionConflict { expected: u32, found: u32 },
+    #[error("Rotation hook failed")]
+    Hook(#[source] HookError),
+    /// The target system has the new value, but the vault does not, and the
+    /// old value could not be restored.  The new value is included so it can
+    /// be recovered by hand.
+    #[error("Vault write failed and rollback failed")]
+    RollbackFailed {
+        #[source]
+        write: Box<Error>,
+        rollback: HookError,
+        new_value: FieldValue,
+    },
+    /// Both the target system and the vault have the new value, but the
+    /// `commit` hook failed.
+    #[error("Rotation commit hook failed")]
+    CommitFailed {
+        rotation: Box<Rotation>,
+        #[source]
+        source: HookError,
+    },
+    #[error("Unsupported database type {0}")]
+    UnsupportedDatabaseType(String),
+    #[error("IO error")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to encrypt cache entry")]
+    Encryption,
+    /// A request shared by concurrent callers failed.
+    #[error("Shared request failed")]
+    Shared(#[source] std::sync::Arc<Error>),
+    #[error("Invalid template {0}")]
+    InvalidTemplate(String),
+    #[error("Reload command failed: {0}")]
+    Reload(String),
+    #[error("Invalid data file {0}")]
+    InvalidDataFile(String),
+    #[error("No recorded response for {0}")]
+    NotRecorded(String),
+    #[error("Invalid agent policy: {0}")]
+    InvalidPolicy(String),
+}
+
+impl Error {
+    /// Whether Connect could not be reached, or could not reach 1Password,
+    /// as opposed to rejecting the request.
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            Error::Reqwest(e) => e.is_connect() || e.is_timeout(),
+            Error::Api { status, .. } => *status >= 500,
+            Error::Shared(e) => e.is_unavailable(),
+            _ => false,
+        }
+    }
+}
+
+/// Error body returned by Connect.
+#[derive(Deserialize)]
+pub(crate) struct ApiErrorBody {
+    pub message: String,
 }
@@ -0,0 +1,33 @@
+//! Items for unit tests.
+
+use serde_json::{json, Value};
+
+use crate::Item;
+
+/// A DATABASE item with a username and a password field, as JSON, with the
+/// top-level keys of `overrides` replacing its own.
+pub(crate) fn item_json(overrides: Value) -> Value {
+    let mut item = json!({
+        "id": "2fcbqwe9ndg175zg2dzwftvkpa",
+        "title": "db",
+        "vault": {"id": "ftz4pm2xxwmwrsd7rjqn7grzfz"},
+        "category": "DATABASE",
+        "version": 1,
+        "createdAt": "2020-01-01T00:00:00Z",
+        "updatedAt": "2020-01-01T00:00:00Z",
+        "lastEditedBy": "LLQ3MFSYSJBQVCOLR4K4SGFCDE",
+        "fields": [
+            {"id": "username", "type": "STRING", "label": "username", "value": "admin"},
+            {"id": "password", "type": "CONCEALED", "label": "password", "value": "hunter2"}
+        ]
+    });
+    if let Value::Object(overrides) = overrides {
+        item.as_object_mut().unwrap().extend(overrides);
+    }
+    item
+}
+
+/// [item_json], parsed.
+pub(crate) fn item(overrides: Value) -> Item {
+    serde_json::from_value(item_json(overrides)).expect("failed to parse item")
+}
@@ -0,0 +1,291 @@
+//! Local password and passphrase generation.
+//!
+//! Values are generated with the operating system CSPRNG, so credentials can
+//! be created offline, for tests or before Connect is reachable.
+//! ```rust
+//! use op_connect_rs::{generate, CharacterSet, GeneratorRecipe};
+//!
+//! let recipe = GeneratorRecipe {
+//!     character_sets: vec![CharacterSet::Letters, CharacterSet::Digits],
+//!     length: 20,
+//!     exclude_characteres: Some("0O1l".to_owned()),
+//! };
+//! let generated = generate(&recipe).unwrap();
+//! assert_eq!(generated.value.len(), 20);
+//! assert!(generated.entropy > 100.0);
+//! ```
+use rand::{rngs::OsRng, seq::SliceRandom, Rng};
+use zeroize::Zeroizing;
+
+use crate::{CharacterSet, Error, FieldValue, GeneratorRecipe, Result};
+
+const LETTERS: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const DIGITS: &str = "0123456789";
+/// Symbols that are safe to paste into shells and config files: no quotes,
+/// backslash or backtick.
+const SYMBOLS: &str = "!#$%&()*+,-./:;<=>?@[]^_{|}~";
+
+/// Words for passphrases: the BIP-39 English list.  2048 words, so each word
+/// adds 11 bits of entropy.
+const WORDLIST: &str = include_str!("wordlist.txt");
+
+/// Longest password [generate] will produce.
+pub const MAX_GENERATED_LENGTH: u32 = 1024;
+
+/// A generated value and its entropy in bits.
+#[derive(Debug, Clone)]
+pub struct Generated {
+    pub value: FieldValue,
+    pub entropy: f64,
+}
+
+/// Diceware-style passphrase recipe.
+#[derive(Debug, Clone)]
+pub struct PassphraseRecipe {
+    /// Number of words.  Defaults to 5.
+    pub words: u32,
+    /// Placed between words.  Defaults to "-".
+    pub separator: String,
+    /// Capitalize the first letter of each word.
+    pub capitalize: bool,
+}
+
+impl Default for PassphraseRecipe {
+    fn default() -> Self {
+        Self {
+            words: 5,
+            separator: "-".to_owned(),
+            capitalize: false,
+        }
+    }
+}
+
+impl CharacterSet {
+    /// The characters in the set.
+    pub fn characters(&self) -> &'static str {
+        match self {
+            CharacterSet::Letters => LETTERS,
+            CharacterSet::Digits => DIGITS,
+            CharacterSet::Symbols => SYMBOLS,
+        }
+    }
+}
+
+/// Generate a password from a recipe.
+///
+/// Every character set in the recipe is guaranteed to appear at least once.
+/// Candidates are drawn uniformly and rejected until they qualify, so the
+/// result is uniform over all qualifying passwords, and the reported entropy
+/// is exact.
+pub fn generate(recipe: &GeneratorRecipe) -> Result<Generated> {
+    let sets = character_sets(recipe)?;
+    let length = recipe.length as usize;
+    if length < sets.len() {
+        return Err(Error::InvalidRecipe(format!(
+            "length {} is too short to include all {} character sets",
+            length,
+            sets.len()
+        )));
+    }
+
+    let pool: Vec<char> = sets.iter().flatten().copied().collect();
+    let mut value = Zeroizing::new(String::with_capacity(length));
+    loop {
+        value.clear();
+        for _ in 0..length {
+            value.push(pool[OsRng.gen_range(0..pool.len())]);
+        }
+        if sets
+            .iter()
+            .all(|set| value.chars().any(|c| set.contains(&c)))
+        {
+            break;
+        }
+    }
+
+    Ok(Generated {
+        value: FieldValue::new(&value),
+        entropy: password_entropy(&sets, length),
+    })
+}
+
+/// Generate a passphrase of random words.
+pub fn generate_passphrase(recipe: &PassphraseRecipe) -> Result<Generated> {
+    if recipe.words == 0 {
+        return Err(Error::InvalidRecipe("words must be at least 1".to_owned()));
+    }
+    let words: Vec<&str> = WORDLIST.lines().collect();
+    let mut value = Zeroizing::new(String::with_capacity(
+        recipe.words as usize * (8 + recipe.separator.len()),
+    ));
+    for i in 0..recipe.words {
+        if i > 0 {
+            value.push_str(&recipe.separator);
+        }
+        let word = words.choose(&mut OsRng).expect("wordlist is empty");
+        if recipe.capitalize {
+            let mut chars = word.chars();
+            if let Some(first) = chars.next() {
+                value.extend(first.to_uppercase());
+            }
+            value.push_str(chars.as_str());
+        } else {
+            value.push_str(word);
+        }
+    }
+
+    Ok(Generated {
+        value: FieldValue::new(&value),
+        entropy: recipe.words as f64 * (words.len() as f64).log2(),
+    })
+}
+
+/// The recipe's character sets, less any excluded characters.
+fn character_sets(recipe: &GeneratorRecipe) -> Result<Vec<Vec<char>>> {
+    if recipe.length == 0 || recipe.length > MAX_GENERATED_LENGTH {
+        return Err(Error::InvalidRecipe(format!(
+            "length must be between 1 and {}",
+            MAX_GENERATED_LENGTH
+        )));
+    }
+    if recipe.character_sets.is_empty() {
+        return Err(Error::InvalidRecipe("no character sets".to_owned()));
+    }
+    let exclude = recipe.exclude_characteres.as_deref().unwrap_or_default();
+
+    let mut sets: Vec<CharacterSet> = Vec::new();
+    for set in &recipe.character_sets {
+        if !sets.contains(set) {
+            sets.push(set.clone());
+        }
+    }
+    sets.iter()
+        .map(|set| {
+            let chars: Vec<char> = set
+                .characters()
+                .chars()
+                .filter(|c| !exclude.contains(*c))
+                .collect();
+            if chars.is_empty() {
+                Err(Error::InvalidRecipe(format!(
+                    "every character in {:?} is excluded",
+                    set
+                )))
+            } else {
+                Ok(chars)
+            }
+        })
+        .collect()
+}
+
+/// log2 of the number of passwords of the given length that include every
+/// set, by inclusion-exclusion over the sets that are missing.  The sets are
+/// disjoint.
+fn password_entropy(sets: &[Vec<char>], length: usize) -> f64 {
+    let pool = sets.iter().map(Vec::len).sum::<usize>() as f64;
+    let mut fraction = 0.0;
+    for missing in 0..(1u32 << sets.len()) {
+        let removed: usize = sets
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| missing & (1 << i) != 0)
+            .map(|(_, set)| set.len())
+            .sum();
+        let term = ((pool - removed as f64) / pool).powi(length as i32);
+        if missing.count_ones() % 2 == 0 {
+            fraction += term;
+        } else {
+            fraction -= term;
+        }
+    }
+    length as f64 * pool.log2() + fraction.log2()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recipe(character_sets: Vec<CharacterSet>, length: u32) -> GeneratorRecipe {
+        GeneratorRecipe {
+            character_sets,
+            length,
+            exclude_characteres: None,
+        }
+    }
+
+    #[test]
+    fn test_includes_every_set() {
+        let recipe = recipe(
+            vec![
+                CharacterSet::Letters,
+                CharacterSet::Digits,
+                CharacterSet::Symbols,
+            ],
+            3,
+        );
+        for _ in 0..100 {
+            let generated = generate(&recipe).unwrap();
+            let value = generated.value.inner();
+            assert_eq!(value.chars().count(), 3);
+            assert!(value.chars().any(|c| c.is_ascii_alphabetic()));
+            assert!(value.chars().any(|c| c.is_ascii_digit()));
+            assert!(value.chars().any(|c| SYMBOLS.contains(c)));
+        }
+    }
+
+    #[test]
+    fn test_excluded_characters() {
+        let mut recipe = recipe(vec![CharacterSet::Digits], 64);
+        recipe.exclude_characteres = Some("0123456".to_owned());
+        let generated = generate(&recipe).unwrap();
+        assert!(generated.value.chars().all(|c| "789".contains(c)));
+        assert!((generated.entropy - 64.0 * 3f64.log2()).abs() < 1e-9);
+
+        recipe.exclude_characteres = Some(DIGITS.to_owned());
+        assert!(matches!(generate(&recipe), Err(Error::InvalidRecipe(_))));
+    }
+
+    #[test]
+    fn test_invalid_recipes() {
+        assert!(generate(&recipe(vec![], 10)).is_err());
+        assert!(generate(&recipe(vec![CharacterSet::Digits], 0)).is_err());
+        assert!(generate(&recipe(
+            vec![CharacterSet::Digits, CharacterSet::Letters],
+            1
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_entropy() {
+        // Exactly 2 of the 4 two-character strings over {a} and {b} qualify.
+        let sets = vec![vec!['a'], vec!['b']];
+        assert!((password_entropy(&sets, 2) - 1.0).abs() < 1e-9);
+        let default = generate(&recipe(
+            vec![
+                CharacterSet::Letters,
+                CharacterSet::Digits,
+                CharacterSet::Symbols,
+            ],
+            32,
+        ))
+        .unwrap();
+        assert!(default.entropy > 190.0 && default.entropy < 32.0 * 90f64.log2());
+    }
+
+    #[test]
+    fn test_passphrase() {
+        let generated = generate_passphrase(&PassphraseRecipe {
+            words: 6,
+            separator: " ".to_owned(),
+            capitalize: true,
+        })
+        .unwrap();
+        let words: Vec<&str> = generated.value.split(' ').collect();
+        assert_eq!(words.len(), 6);
+        assert!(words
+            .iter()
+            .all(|w| w.chars().next().unwrap().is_uppercase()));
+        assert_eq!(generated.entropy, 66.0);
+    }
+}
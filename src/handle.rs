@@ -0,0 +1,252 @@
+//! Auto-refreshing secret handles.
+//!
+//! A [SecretHandle] holds the current value of an item, a field, or a typed
+//! view of an item such as a `DatabaseItem`.  It is created by one of the
+//! `OpClient::*_handle` methods, which fetch the item once and then watch it
+//! in the background, in a Tokio task for the async client or a thread for
+//! the blocking one.  When the item changes, the value is rebuilt and the
+//! `on_change` callbacks run.  If the item cannot be fetched, or the value
+//! cannot be built from it, the handle keeps serving the last good value.
+//!
+//! Reading the value with [SecretHandle::load] is cheap: there is no lock,
+//! and no request.  Refreshing stops once every clone of the handle has been
+//! dropped, so a callback that needs the handle should capture a
+//! [WeakSecretHandle] from [SecretHandle::downgrade]: a clone would keep the
+//! handle, and its refresh, alive forever.
+//! ```rust,ignore
+//! let password = client
+//!     .reference_handle("op://prod/Postgres/password", Duration::from_secs(60))
+//!     .await?;
+//! password.on_change(|password| reconnect(password.expose_secret()));
+//! let current = password.load();
+//! ```
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+
+use crate::watch::WatchState;
+use crate::{Error, FieldValue, Item, ItemChanged, Result, Vault};
+
+pub(crate) type Project<T> = Box<dyn Fn(&Item) -> Result<T> + Send + Sync>;
+type Callback<T> = Arc<dyn Fn(&T) + Send + Sync>;
+
+/// The current value of a secret, refreshed in the background.  Clones share
+/// the value.
+pub struct SecretHandle<T> {
+    shared: Arc<HandleState<T>>,
+}
+
+/// A [SecretHandle] that does not keep it alive, for use in its own
+/// callbacks.
+pub struct WeakSecretHandle<T> {
+    shared: Weak<HandleState<T>>,
+}
+
+struct HandleState<T> {
+    value: ArcSwap<T>,
+    project: Project<T>,
+    /// Only refresh when this field changed.
+    field_id: Option<String>,
+    callbacks: Mutex<Vec<Callback<T>>>,
+}
+
+impl<T> Clone for SecretHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Clone for WeakSecretHandle<T> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for SecretHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecretHandle")
+            .field("field_id", &self.shared.field_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> SecretHandle<T> {
+    /// Build the first value from `item`.  Fails if that does.
+    pub(crate) fn new(item: &Item, field_id: Option<String>, project: Project<T>) -> Result<Self> {
+        let value = project(item)?;
+        Ok(Self {
+            shared: Arc::new(HandleState {
+                value: ArcSwap::from_pointee(value),
+                project,
+                field_id,
+                callbacks: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// The current value.
+    pub fn load(&self) -> Arc<T> {
+        self.shared.value.load_full()
+    }
+
+    /// Call `callback` with each new value.  Callbacks may register more
+    /// callbacks, which are first called with the next value.
+    pub fn on_change(&self, callback: impl Fn(&T) + Send + Sync + 'static) {
+        self.shared
+            .callbacks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(Arc::new(callback));
+    }
+
+    /// A reference to this handle that does not keep it alive.  A callback
+    /// that captures a clone of its own handle makes a cycle, and the handle
+    /// is never dropped; capture this instead.
+    pub fn downgrade(&self) -> WeakSecretHandle<T> {
+        WeakSecretHandle {
+            shared: Arc::downgrade(&self.shared),
+        }
+    }
+
+    pub(crate) fn changed(&self, change: &ItemChanged) {
+        self.shared.changed(change)
+    }
+}
+
+impl<T> WeakSecretHandle<T> {
+    /// The handle, unless every clone of it has been dropped.
+    pub fn upgrade(&self) -> Option<SecretHandle<T>> {
+        self.shared.upgrade().map(|shared| SecretHandle { shared })
+    }
+}
+
+impl<T> HandleState<T> {
+    fn changed(&self, change: &ItemChanged) {
+        if let Some(field_id) = &self.field_id {
+            if !change.changed_fields.contains(field_id) {
+                return;
+            }
+        }
+        match (self.project)(&change.new) {
+            Ok(value) => {
+                let value = Arc::new(value);
+                self.value.store(value.clone());
+                // Call them without the lock, so they can call `on_change`.
+                let callbacks = self
+                    .callbacks
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .clone();
+                for callback in callbacks {
+                    callback(&value);
+                }
+            }
+            Err(e) => log::warn!(
+                "keeping the last good value of item {}: {}",
+                change.new.id,
+                e
+            ),
+        }
+    }
+}
+
+/// Watch state for a handle, polling every `refresh` with 10% jitter.
+pub(crate) fn refresh_state(vault: &Vault, item: &Item, refresh: Duration) -> WatchState {
+    let mut state = WatchState::new(vault, std::slice::from_ref(item));
+    state.interval = refresh;
+    state.jitter = refresh / 10;
+    state
+}
+
+/// Builds the value of a field handle.
+pub(crate) fn field_value(field_id: String) -> Project<FieldValue> {
+    Box::new(move |item| {
+        item.fields
+            .iter()
+            .flatten()
+            .find(|field| field.id == field_id)
+            .and_then(|field| field.value.clone())
+            .ok_or_else(|| Error::MissingField(field_id.clone()))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn item(version: u32, password: &str) -> Item {
+        fixtures::item(serde_json::json!({
+            "version": version,
+            "fields": [
+                {"id": "password", "type": "CONCEALED", "label": "password", "value": password}
+            ]
+        }))
+    }
+
+    fn change(new: Item, changed_fields: &[&str]) -> ItemChanged {
+        ItemChanged {
+            old_version: new.version - 1,
+            new,
+            changed_fields: changed_fields.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_refresh() {
+        let handle = SecretHandle::new(
+            &item(1, "hunter2"),
+            Some("password".to_owned()),
+            field_value("password".to_owned()),
+        )
+        .unwrap();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        handle.on_change(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        });
+
+        // Another field changed.
+        handle.changed(&change(item(2, "hunter2"), &["notes"]));
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+        handle.changed(&change(item(3, "correct horse"), &["password"]));
+        assert_eq!(handle.load().expose_secret(), "correct horse");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // The field was removed: keep the last good value.
+        let mut removed = item(4, "");
+        removed.fields = Some(Vec::new());
+        handle.changed(&change(removed, &["password"]));
+        assert_eq!(handle.load().expose_secret(), "correct horse");
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A callback that registers another one does not deadlock.
+        let registering = handle.downgrade();
+        let counter = calls.clone();
+        handle.on_change(move |_| {
+            let counter = counter.clone();
+            let Some(registering) = registering.upgrade() else {
+                return;
+            };
+            registering.on_change(move |_| {
+                counter.fetch_add(10, Ordering::SeqCst);
+            });
+        });
+        handle.changed(&change(item(5, "battery staple"), &["password"]));
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        handle.changed(&change(item(6, "tr0ub4dor"), &["password"]));
+        assert_eq!(calls.load(Ordering::SeqCst), 13);
+
+        // The callback's weak handle does not keep the handle alive.
+        let weak = handle.downgrade();
+        drop(handle);
+        assert!(weak.upgrade().is_none());
+    }
+}
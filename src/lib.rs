@@ -1,9 +1,49 @@
 #![doc = include_str!("../README.md")]
+pub use audit::*;
+pub use cache::CacheStats;
+pub use cassette::Cassette;
+pub use disk_cache::*;
 pub use error::*;
+pub use generator::*;
+pub use handle::{SecretHandle, WeakSecretHandle};
 pub use model::*;
+pub use rotator::*;
+pub use sink::*;
+#[cfg(not(feature = "blocking"))]
+pub use store::StoreFuture;
+pub use store::{ChainStore, EnvStore, InMemoryStore, SecretStore};
+pub use strength::*;
+pub use watch::{ItemChanged, DEFAULT_WATCH_INTERVAL, DEFAULT_WATCH_JITTER};
 
+// The agent serves with the async client.
+#[cfg(all(unix, feature = "agent", not(feature = "blocking")))]
+pub mod agent;
+mod atomic_file;
+pub mod audit;
+pub mod cache;
+pub mod cassette;
+pub mod disk_cache;
+#[cfg(feature = "emulator")]
+pub mod emulator;
 pub mod error;
+#[cfg(test)]
+mod fixtures;
+pub mod generator;
+#[cfg_attr(not(any(feature = "async", feature = "blocking")), allow(dead_code))]
+pub mod handle;
 pub mod model;
+pub mod rotator;
+mod secure_memory;
+#[cfg(all(feature = "async", not(feature = "blocking")))]
+mod single_flight;
+pub mod sink;
+pub mod store;
+pub mod strength;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+// Watching needs a timer, which the async client only has with `async`.
+#[cfg_attr(not(any(feature = "async", feature = "blocking")), allow(dead_code))]
+pub mod watch;
 
 #[cfg(not(feature = "blocking"))]
 pub use async_lib::*;
@@ -0,0 +1,111 @@
+use chrono::NaiveDate;
+
+use super::{Category, FieldValue, Item};
+use crate::{Error, Result};
+
+/// Typed view of an API_CREDENTIAL item.
+///
+/// The credential is required.
+#[derive(Debug, Clone)]
+pub struct ApiCredentialItem {
+    pub username: Option<String>,
+    pub credential: FieldValue,
+    /// The "type" menu value, such as "bearer" or "jwt".
+    pub credential_type: Option<String>,
+    pub filename: Option<String>,
+    pub valid_from: Option<NaiveDate>,
+    pub expires: Option<NaiveDate>,
+    pub hostname: Option<String>,
+}
+
+impl TryFrom<&Item> for ApiCredentialItem {
+    type Error = Error;
+
+    fn try_from(item: &Item) -> Result<Self> {
+        item.expect_category(Category::ApiCredential)?;
+        Ok(Self {
+            username: item.find_string(&["username"]),
+            credential: item.require_value(&["credential"])?,
+            credential_type: item.find_string(&["type"]),
+            filename: item.find_string(&["filename"]),
+            valid_from: item.find_date(&["validFrom", "valid from"])?,
+            expires: item.find_date(&["expires"])?,
+            hostname: item.find_string(&["hostname"]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn api_credential_item() -> Item {
+        fixtures::item(serde_json::json!({
+            "title": "Stripe",
+            "category": "API_CREDENTIAL",
+            "fields": [
+                {"id": "username", "type": "STRING", "label": "username", "value": "acct_1Example"},
+                {"id": "credential", "type": "CONCEALED", "label": "credential", "value": "sk_test_4eC39HqLyjWDarjtT1zdp7dc"},
+                {"id": "type", "type": "MENU", "label": "type", "value": "bearer"},
+                {"id": "filename", "type": "STRING", "label": "filename"},
+                {"id": "validFrom", "type": "DATE", "label": "valid from", "value": "1704067200"},
+                {"id": "expires", "type": "DATE", "label": "expires", "value": "2026-12-31"},
+                {"id": "hostname", "type": "STRING", "label": "hostname", "value": "api.stripe.com"}
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_api_credential() {
+        let credential = ApiCredentialItem::try_from(&api_credential_item()).unwrap();
+        assert_eq!(credential.username.as_deref(), Some("acct_1Example"));
+        assert_eq!(
+            credential.credential.expose_secret(),
+            "sk_test_4eC39HqLyjWDarjtT1zdp7dc"
+        );
+        assert_eq!(credential.credential_type.as_deref(), Some("bearer"));
+        assert!(credential.filename.is_none());
+        // Connect sends some dates as Unix timestamps.
+        assert_eq!(credential.valid_from, NaiveDate::from_ymd_opt(2024, 1, 1));
+        assert_eq!(credential.expires, NaiveDate::from_ymd_opt(2026, 12, 31));
+        assert_eq!(credential.hostname.as_deref(), Some("api.stripe.com"));
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let mut item = api_credential_item();
+        item.fields
+            .as_mut()
+            .unwrap()
+            .retain(|field| field.id != "credential");
+        assert!(matches!(
+            ApiCredentialItem::try_from(&item),
+            Err(Error::MissingField(field)) if field == "credential"
+        ));
+
+        let mut item = api_credential_item();
+        for field in item.fields.iter_mut().flatten() {
+            if field.id == "expires" {
+                field.value = Some(FieldValue::new("next year"));
+            }
+        }
+        assert!(matches!(
+            ApiCredentialItem::try_from(&item),
+            Err(Error::InvalidField(field)) if field == "expires"
+        ));
+    }
+
+    #[test]
+    fn test_wrong_category() {
+        let mut item = api_credential_item();
+        item.category = Category::Password;
+        assert!(matches!(
+            ApiCredentialItem::try_from(&item),
+            Err(Error::CategoryMismatch {
+                expected: Category::ApiCredential,
+                ..
+            })
+        ));
+    }
+}
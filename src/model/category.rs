@@ -2,6 +2,9 @@ use serde::{Deserialize, Serialize};
 
 /// Item Category
 /// You can't create items using the "CUSTOM" or "DOCUMENT" categories.
+///
+/// Categories added to 1Password after this crate was released deserialize
+/// to `Unknown`, and serialize back unchanged.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum Category {
@@ -26,5 +29,26 @@ pub enum Category {
     SocialSecurityNumber,
     MedicalRecord,
     SshKey,
+    CryptoWallet,
     Custom,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for (json, category) in [
+            ("\"SSH_KEY\"", Category::SshKey),
+            ("\"CRYPTO_WALLET\"", Category::CryptoWallet),
+            ("\"API_CREDENTIAL\"", Category::ApiCredential),
+            ("\"PASSKEY\"", Category::Unknown("PASSKEY".to_owned())),
+        ] {
+            assert_eq!(serde_json::from_str::<Category>(json).unwrap(), category);
+            assert_eq!(serde_json::to_string(&category).unwrap(), json);
+        }
+    }
 }
@@ -0,0 +1,130 @@
+use super::{Category, FieldValue, Item, MonthYear};
+use crate::{Error, Result};
+
+/// Typed view of a CREDIT_CARD item.
+///
+/// The card number is required.  An expiry that is present but not in
+/// `YYYYMM` or `YYYY/MM` format results in [Error::InvalidField].
+#[derive(Debug, Clone)]
+pub struct CreditCardItem {
+    pub cardholder: Option<String>,
+    /// The card type, such as "visa" or "mc".
+    pub card_type: Option<String>,
+    pub number: FieldValue,
+    pub verification_number: Option<FieldValue>,
+    pub expiry: Option<MonthYear>,
+    pub valid_from: Option<MonthYear>,
+    pub pin: Option<FieldValue>,
+}
+
+impl TryFrom<&Item> for CreditCardItem {
+    type Error = Error;
+
+    fn try_from(item: &Item) -> Result<Self> {
+        item.expect_category(Category::CreditCard)?;
+        Ok(Self {
+            cardholder: item.find_string(&["cardholder", "cardholder name"]),
+            card_type: item.find_string(&["type"]),
+            number: item.require_value(&["ccnum", "number"])?,
+            verification_number: item.find_value(&["cvv", "verification number"]),
+            expiry: item.find_month_year(&["expiry", "expiry date"])?,
+            valid_from: item.find_month_year(&["validFrom", "valid from"])?,
+            pin: item.find_value(&["pin", "PIN"]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn credit_card_item() -> Item {
+        fixtures::item(serde_json::json!({
+            "title": "Visa",
+            "category": "CREDIT_CARD",
+            "sections": [{"id": "details", "label": "Additional Details"}],
+            "fields": [
+                {"id": "cardholder", "type": "STRING", "label": "cardholder name", "value": "Wendy Appleseed"},
+                {"id": "type", "type": "CREDIT_CARD_TYPE", "label": "type", "value": "visa"},
+                {"id": "ccnum", "type": "CREDIT_CARD_NUMBER", "label": "number", "value": "4111111111111111"},
+                {"id": "cvv", "type": "CONCEALED", "label": "verification number", "value": "123"},
+                {"id": "expiry", "type": "MONTH_YEAR", "label": "expiry date", "value": "202612"},
+                {"id": "validFrom", "type": "MONTH_YEAR", "label": "valid from", "value": "202112"},
+                {"id": "exxdxea4eszhi72kiosbdq334o", "type": "CONCEALED", "label": "PIN", "value": "0000", "section": {"id": "details"}}
+            ]
+        }))
+    }
+
+    fn with_expiry(expiry: &str) -> Item {
+        let mut item = credit_card_item();
+        for field in item.fields.iter_mut().flatten() {
+            if field.id == "expiry" {
+                field.value = Some(FieldValue::new(expiry));
+            }
+        }
+        item
+    }
+
+    #[test]
+    fn test_credit_card() {
+        let card = CreditCardItem::try_from(&credit_card_item()).unwrap();
+        assert_eq!(card.cardholder.as_deref(), Some("Wendy Appleseed"));
+        assert_eq!(card.card_type.as_deref(), Some("visa"));
+        assert_eq!(card.number.expose_secret(), "4111111111111111");
+        assert_eq!(
+            card.verification_number.as_ref().map(|v| v.expose_secret()),
+            Some("123")
+        );
+        assert_eq!(card.expiry, MonthYear::new(2026, 12));
+        assert_eq!(card.valid_from, MonthYear::new(2021, 12));
+        assert_eq!(card.pin.as_ref().map(|v| v.expose_secret()), Some("0000"));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let card = CreditCardItem::try_from(&with_expiry("2027/03")).unwrap();
+        assert_eq!(card.expiry, MonthYear::new(2027, 3));
+        assert_eq!(card.expiry.unwrap().last_day().to_string(), "2027-03-31");
+
+        let card = CreditCardItem::try_from(&with_expiry("")).unwrap();
+        assert!(card.expiry.is_none());
+
+        for expiry in ["12/26", "202613", "next year"] {
+            assert!(
+                matches!(
+                    CreditCardItem::try_from(&with_expiry(expiry)),
+                    Err(Error::InvalidField(field)) if field == "expiry"
+                ),
+                "{} parsed",
+                expiry
+            );
+        }
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let mut item = credit_card_item();
+        item.fields
+            .as_mut()
+            .unwrap()
+            .retain(|field| field.id != "ccnum");
+        assert!(matches!(
+            CreditCardItem::try_from(&item),
+            Err(Error::MissingField(field)) if field == "ccnum"
+        ));
+    }
+
+    #[test]
+    fn test_wrong_category() {
+        let mut item = credit_card_item();
+        item.category = Category::BankAccount;
+        assert!(matches!(
+            CreditCardItem::try_from(&item),
+            Err(Error::CategoryMismatch {
+                expected: Category::CreditCard,
+                ..
+            })
+        ));
+    }
+}
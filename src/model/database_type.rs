@@ -0,0 +1,64 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// Value of the "type" menu on a Database item.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DatabaseType {
+    Db2,
+    Filemaker,
+    Msaccess,
+    Mssql,
+    Mysql,
+    Oracle,
+    Postgresql,
+    Sqlite,
+    Mongodb,
+    Redis,
+    /// Any database type not listed above, including 1Password's "other".
+    Other(String),
+}
+
+impl From<&str> for DatabaseType {
+    fn from(s: &str) -> Self {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "db2" => Self::Db2,
+            "filemaker" => Self::Filemaker,
+            "msaccess" => Self::Msaccess,
+            "mssql" | "sqlserver" => Self::Mssql,
+            "mysql" | "mariadb" => Self::Mysql,
+            "oracle" => Self::Oracle,
+            "postgresql" | "postgres" => Self::Postgresql,
+            "sqlite" => Self::Sqlite,
+            "mongodb" | "mongo" => Self::Mongodb,
+            "redis" => Self::Redis,
+            _ => Self::Other(s.to_owned()),
+        }
+    }
+}
+
+impl FromStr for DatabaseType {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::from(s))
+    }
+}
+
+impl fmt::Display for DatabaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Db2 => "db2",
+            Self::Filemaker => "filemaker",
+            Self::Msaccess => "msaccess",
+            Self::Mssql => "mssql",
+            Self::Mysql => "mysql",
+            Self::Oracle => "oracle",
+            Self::Postgresql => "postgresql",
+            Self::Sqlite => "sqlite",
+            Self::Mongodb => "mongodb",
+            Self::Redis => "redis",
+            Self::Other(other) => other,
+        };
+        f.write_str(name)
+    }
+}
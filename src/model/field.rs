@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-use super::{FieldPurpose, FieldType, FieldValue};
+use super::{FieldPurpose, FieldType, FieldValue, GeneratorRecipe, Section};
 
 // Item field object
 /// ```json
@@ -16,17 +16,44 @@ use super::{FieldPurpose, FieldType, FieldValue};
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Field {
     pub id: String,
+    /// An object containing the UUID of the section the field is in.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub section: Option<Section>,
     pub label: String,
     #[serde(rename = "type")]
     pub _type: FieldType,
     /// Use purpose for the username, password, and notes fields
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub purpose: Option<FieldPurpose>,
     /// The value to save for the field.
     /// You can specify a generate field instead of value to create a password or other
     /// random information for the value.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub value: Option<FieldValue>,
     /// Generate a password and save in the value for the field. By default,
     /// the password is a 32-characters long, made up of letters, numbers, and symbols.
     /// To customize the password, include a recipe field.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub generate: Option<bool>,
+    /// The recipe used to generate the value.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recipe: Option<GeneratorRecipe>,
+    /// For a password, the estimated entropy in bits.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entropy: Option<f64>,
+    /// For an OTP field, the current one-time password.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub totp: Option<FieldValue>,
+    /// The secret reference for the field, in the form `op://vault/item/[section/]field`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reference: Option<String>,
+}
+
+impl Field {
+    /// Whether the field belongs to the section with the given id.
+    pub fn in_section(&self, section_id: &str) -> bool {
+        self.section
+            .as_ref()
+            .is_some_and(|section| section.id == section_id)
+    }
 }
@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum FieldPurpose {
     Username,
     Password,
     Notes,
+    /// A purpose this crate does not know about.
+    #[serde(untagged)]
+    Unknown(String),
 }
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// Field types added to 1Password after this crate was released deserialize
+/// to `Unknown`, and serialize back unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum FieldType {
     String,
@@ -11,4 +13,49 @@ pub enum FieldType {
     Date,      // format: YYYY-MM-DD
     MonthYear, // format: YYYYMM or YYYY/MM
     Menu,
+    Phone,
+    Address,
+    #[serde(rename = "SSHKEY")]
+    SshKey,
+    Reference,
+    CreditCardType,
+    CreditCardNumber,
+    Gender,
+    File,
+    #[serde(untagged)]
+    Unknown(String),
+}
+
+impl FieldType {
+    /// Whether values of this type are secret.  Unknown types are assumed
+    /// to be.
+    pub fn is_secret(&self) -> bool {
+        matches!(
+            self,
+            FieldType::Concealed
+                | FieldType::Otp
+                | FieldType::SshKey
+                | FieldType::CreditCardNumber
+                | FieldType::Unknown(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for (json, field_type) in [
+            ("\"SSHKEY\"", FieldType::SshKey),
+            ("\"CREDIT_CARD_NUMBER\"", FieldType::CreditCardNumber),
+            ("\"MONTH_YEAR\"", FieldType::MonthYear),
+            ("\"OTP\"", FieldType::Otp),
+            ("\"PASSKEY\"", FieldType::Unknown("PASSKEY".to_owned())),
+        ] {
+            assert_eq!(serde_json::from_str::<FieldType>(json).unwrap(), field_type);
+            assert_eq!(serde_json::to_string(&field_type).unwrap(), json);
+        }
+    }
 }
@@ -1,40 +1,139 @@
+use std::fmt;
 use std::ops::Deref;
 
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
-/// Zeroized value to ensure secure cleanup.  Note, if you clone the
+use crate::secure_memory::SecretBuf;
+
+/// Zeroized value to ensure secure cleanup.  Note, if you copy the
 /// field value inner, you are responsible for cleanup.
 ///
-/// Deref is supported.  
+/// With the `mlock` feature, values are kept in page-locked memory with guard
+/// pages, and excluded from core dumps on Linux.  Cloning a FieldValue copies
+/// it straight into new locked memory.
+///
+/// `Debug` and `Display` are redacted, so values do not end up in logs.  Use
+/// [FieldValue::expose_secret] to get at the value, or [FieldValue::reveal]
+/// to format it while debugging.
+///
+/// Deref to `String` is supported.  With the `mlock` feature the value is not
+/// a `String`, so it derefs to `str` instead.
 /// ```rust
 /// use op_connect_rs::FieldValue;
 ///
-/// fn print_value() {
-///     let field_value = FieldValue::new("secret");
-///     println!("Value: {}", *field_value);
-/// }
+/// let field_value = FieldValue::new("secret");
+/// assert_eq!(format!("{}", field_value), "[REDACTED]");
+/// assert_eq!(format!("{}", field_value.reveal()), "secret");
+/// assert_eq!(field_value.expose_secret(), "secret");
 /// ```
 ///
-#[derive(Debug, Clone, Deserialize, Serialize, Zeroize, ZeroizeOnDrop)]
-pub struct FieldValue(String);
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
+pub struct FieldValue(SecretBuf);
+
+/// Placeholder written in place of secrets by `Debug` and `Display`.
+pub const REDACTED: &str = "[REDACTED]";
 
 impl FieldValue {
     /// Create a FieldValue.  This is just for testing.
     pub fn new(value: &str) -> Self {
-        Self(value.to_string())
+        Self(SecretBuf::from_str(value))
     }
 
     /// Returns a reference to the actual value.
     pub fn inner(&self) -> &str {
-        &self.0
+        self.0.as_str()
+    }
+
+    /// Returns a reference to the actual value.  Same as [FieldValue::inner],
+    /// but easier to spot in review.
+    pub fn expose_secret(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Opt in to formatting the actual value, for debugging.
+    pub fn reveal(&self) -> Reveal<'_> {
+        Reveal(self)
+    }
+}
+
+/// Values are copied exactly once, into an allocation of exactly the right
+/// size (or locked memory), so no partial copies are left behind by
+/// reallocation.  Strings
+/// containing JSON escapes are unescaped into serde_json's scratch buffer
+/// first, which is outside of our control.
+impl<'de> Deserialize<'de> for FieldValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FieldValueVisitor;
+
+        impl<'de> de::Visitor<'de> for FieldValueVisitor {
+            type Value = FieldValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<FieldValue, E> {
+                Ok(FieldValue(SecretBuf::from_str(v)))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<FieldValue, E> {
+                Ok(FieldValue(SecretBuf::from_string(v)))
+            }
+        }
+
+        deserializer.deserialize_string(FieldValueVisitor)
+    }
+}
+
+impl Serialize for FieldValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.0.as_str())
+    }
+}
+
+impl fmt::Debug for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FieldValue")
+            .field(&format_args!("{}", REDACTED))
+            .finish()
     }
 }
 
+impl fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(REDACTED)
+    }
+}
+
+/// Formats the actual value of a [FieldValue].  See [FieldValue::reveal].
+pub struct Reveal<'a>(&'a FieldValue);
+
+impl fmt::Debug for Reveal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FieldValue").field(&self.0.inner()).finish()
+    }
+}
+
+impl fmt::Display for Reveal<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.inner())
+    }
+}
+
+#[cfg(not(all(feature = "mlock", unix)))]
 impl Deref for FieldValue {
     type Target = String;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        self.0.as_string()
+    }
+}
+
+#[cfg(all(feature = "mlock", unix))]
+impl Deref for FieldValue {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        self.0.as_str()
     }
 }
 
@@ -46,6 +145,31 @@ mod test {
     fn test_deref() {
         let secret = "secret";
         let field_value = FieldValue::new(secret);
+        #[cfg(not(all(feature = "mlock", unix)))]
         assert_eq!(*field_value, secret);
+        #[cfg(all(feature = "mlock", unix))]
+        assert_eq!(&*field_value, secret);
+    }
+
+    #[test]
+    fn test_deserialize() {
+        let value: FieldValue = serde_json::from_slice(br#""plain""#).unwrap();
+        assert_eq!(value.inner(), "plain");
+        #[cfg(not(all(feature = "mlock", unix)))]
+        assert_eq!(value.0.as_string().capacity(), value.0.as_str().len());
+        let value: FieldValue = serde_json::from_slice(br#""esc\"aped""#).unwrap();
+        assert_eq!(value.inner(), "esc\"aped");
+        #[cfg(not(all(feature = "mlock", unix)))]
+        assert_eq!(value.0.as_string().capacity(), value.0.as_str().len());
+    }
+
+    #[test]
+    fn test_redacted() {
+        let field_value = FieldValue::new("hunter2");
+        assert!(!format!("{:?}", field_value).contains("hunter2"));
+        assert!(!format!("{:#?}", field_value).contains("hunter2"));
+        assert!(!format!("{}", field_value).contains("hunter2"));
+        assert!(format!("{:?}", field_value.reveal()).contains("hunter2"));
+        assert_eq!(field_value.reveal().to_string(), "hunter2");
     }
 }
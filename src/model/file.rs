@@ -27,7 +27,9 @@ pub struct File {
     /// The path to download the contents of the file.
     pub content_path: String,
     /// The Base64-encoded contents of the file, if inline_files is set to true.
-    pub content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
     /// An object containing the UUID of a section in the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub section: Option<Section>,
 }
@@ -25,6 +25,11 @@ pub struct GeneratorRecipe {
     #[serde(default = "default_length")]
     pub length: u32,
     /// A list of all characters that should be excluded from generated passwords.
+    #[serde(
+        rename = "excludeCharacters",
+        alias = "excludeCharacteres",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub exclude_characteres: Option<String>,
 }
 
@@ -1,7 +1,11 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
-use super::{Category, Field, FieldValue, File, Section, Url, Vault};
+use super::{
+    ApiCredentialItem, Category, CreditCardItem, DatabaseItem, Field, FieldValue, File, LoginItem,
+    MonthYear, Section, ServerItem, SshKeyItem, Url, Vault,
+};
+use crate::{Error, Result};
 
 ///
 /// ```json
@@ -99,24 +103,36 @@ pub struct Item {
     pub vault: Vault,
     /// The category of the item.
     pub category: Category,
-    /// Array of [ItemUrl] objects containing URLs for the item
+    /// Array of [Url] objects containing URLs for the item
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub urls: Option<Vec<Url>>,
     /// Whether the item is marked as a favorite.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub favorite: Option<bool>,
     /// An array of strings of the tags assigned to the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub tags: Option<Vec<String>>,
     /// The version of the item.
     pub version: u32,
+    /// The state of the item, such as "ARCHIVED".  Absent for active items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
     /// Date and time when the item was created.
     pub created_at: DateTime<Utc>,
     /// Date and time when the vault or its contents were last changed.
     pub updated_at: DateTime<Utc>,
-    /// UUID of the account that last changed the item.
-    pub last_edited_by: String,
+    /// UUID of the account that last changed the item.  Absent for items
+    /// not yet saved, and for some items created before it was tracked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_edited_by: Option<String>,
     /// An object containing the UUID of a section in the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub additional_information: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub fields: Option<Vec<Field>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub files: Option<Vec<File>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub sections: Option<Vec<Section>>,
 }
 
@@ -126,7 +142,7 @@ impl Item {
             return None;
         };
 
-        if let Some(field) = fields.iter().filter(|field| field.id == id).last() {
+        if let Some(field) = fields.iter().rfind(|field| field.id == id) {
             return field.value.clone();
         }
         None
@@ -134,15 +150,172 @@ impl Item {
 }
 
 impl Item {
-    pub fn get_field_by_label(&self, label: &str) -> Option<FieldValue> {
-        let Some(fields) = &self.fields else {
-            return None;
-        };
+    /// Get the value of the field with the given label.  Returns
+    /// [Error::AmbiguousField] if more than one field, across all sections,
+    /// has the label.  Use [Item::get_field] to narrow to a section.
+    pub fn get_field_by_label(&self, label: &str) -> Result<FieldValue> {
+        let field = unique_field(self.fields().filter(|field| field.label == label), label)?;
+        field.value.clone().ok_or(Error::NotFound)
+    }
 
-        if let Some(field) = fields.iter().filter(|field| field.label == label).last() {
-            return field.value.clone();
+    /// Get the field with the given label in a section.  The section is
+    /// matched on either its id or label.
+    pub fn get_field(&self, section: &str, field_label: &str) -> Result<&Field> {
+        let section_ids: Vec<&str> = self
+            .sections
+            .iter()
+            .flatten()
+            .filter(|s| s.id == section || s.label.as_deref() == Some(section))
+            .map(|s| s.id.as_str())
+            .collect();
+        unique_field(
+            self.fields().filter(|field| {
+                field.label == field_label && section_ids.iter().any(|id| field.in_section(id))
+            }),
+            field_label,
+        )
+    }
+
+    /// Iterate over the fields of the item, grouped by section.  Fields that
+    /// are not in any section, or whose section is not listed in `sections`,
+    /// come first, with a section of None.  Sections without fields are
+    /// skipped, so every field is yielded exactly once.
+    pub fn fields_by_section(&self) -> impl Iterator<Item = (Option<&Section>, Vec<&Field>)> {
+        let mut sections: Vec<&Section> = Vec::new();
+        for section in self.sections.iter().flatten() {
+            if !sections.iter().any(|s| s.id == section.id) {
+                sections.push(section);
+            }
+        }
+        let unsectioned: Vec<&Field> = self
+            .fields()
+            .filter(|field| match &field.section {
+                Some(section) => !sections.iter().any(|s| s.id == section.id),
+                None => true,
+            })
+            .collect();
+        let sectioned = sections.into_iter().map(|section| {
+            let fields: Vec<&Field> = self
+                .fields()
+                .filter(|field| field.in_section(&section.id))
+                .collect();
+            (Some(section), fields)
+        });
+        std::iter::once((None, unsectioned))
+            .chain(sectioned)
+            .filter(|(_, fields)| !fields.is_empty())
+    }
+
+    fn fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().flatten()
+    }
+}
+
+/// Parse the value of a DATE field, either `YYYY-MM-DD` or a unix timestamp
+/// in seconds.
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Some(date);
+    }
+    value
+        .parse::<i64>()
+        .ok()
+        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+        .map(|date| date.date_naive())
+}
+
+pub(crate) fn unique_field<'a>(
+    mut fields: impl Iterator<Item = &'a Field>,
+    label: &str,
+) -> Result<&'a Field> {
+    let field = fields.next().ok_or(Error::NotFound)?;
+    if fields.next().is_some() {
+        return Err(Error::AmbiguousField(label.to_owned()));
+    }
+    Ok(field)
+}
+
+/// Typed views of the well known categories.  Each returns None if the item
+/// is of a different category or is missing a required field.  Use `TryFrom`
+/// on the view type to find out why.
+impl Item {
+    pub fn as_login(&self) -> Option<LoginItem> {
+        LoginItem::try_from(self).ok()
+    }
+
+    pub fn as_database(&self) -> Option<DatabaseItem> {
+        DatabaseItem::try_from(self).ok()
+    }
+
+    pub fn as_api_credential(&self) -> Option<ApiCredentialItem> {
+        ApiCredentialItem::try_from(self).ok()
+    }
+
+    pub fn as_ssh_key(&self) -> Option<SshKeyItem> {
+        SshKeyItem::try_from(self).ok()
+    }
+
+    pub fn as_credit_card(&self) -> Option<CreditCardItem> {
+        CreditCardItem::try_from(self).ok()
+    }
+
+    pub fn as_server(&self) -> Option<ServerItem> {
+        ServerItem::try_from(self).ok()
+    }
+}
+
+/// Helpers for the typed views.  Well known fields are matched on either id
+/// or label, and empty values are treated as missing.
+impl Item {
+    pub(crate) fn expect_category(&self, expected: Category) -> Result<()> {
+        if self.category == expected {
+            Ok(())
+        } else {
+            Err(Error::CategoryMismatch {
+                expected,
+                found: self.category.clone(),
+            })
+        }
+    }
+
+    pub(crate) fn find_value(&self, keys: &[&str]) -> Option<FieldValue> {
+        let fields = self.fields.as_ref()?;
+        keys.iter().find_map(|key| {
+            fields
+                .iter()
+                .filter(|field| field.id == *key || field.label == *key)
+                .find_map(|field| field.value.clone())
+                .filter(|value| !value.is_empty())
+        })
+    }
+
+    pub(crate) fn find_string(&self, keys: &[&str]) -> Option<String> {
+        self.find_value(keys).map(|value| value.inner().to_owned())
+    }
+
+    pub(crate) fn require_value(&self, keys: &[&str]) -> Result<FieldValue> {
+        self.find_value(keys)
+            .ok_or_else(|| Error::MissingField(keys[0].to_owned()))
+    }
+
+    pub(crate) fn find_date(&self, keys: &[&str]) -> Result<Option<NaiveDate>> {
+        match self.find_value(keys) {
+            Some(value) => parse_date(&value)
+                .map(Some)
+                .ok_or_else(|| Error::InvalidField(keys[0].to_owned())),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) fn find_month_year(&self, keys: &[&str]) -> Result<Option<MonthYear>> {
+        match self.find_value(keys) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| Error::InvalidField(keys[0].to_owned())),
+            None => Ok(None),
         }
-        None
     }
 }
 
@@ -164,9 +337,14 @@ impl std::fmt::Debug for Item {
             dbg.field("tags", &self.tags);
         }
         dbg.field("version", &self.version);
+        if self.state.is_some() {
+            dbg.field("state", &self.state);
+        }
         dbg.field("created_at", &self.created_at);
         dbg.field("updated_at", &self.updated_at);
-        dbg.field("last_edited_by", &self.last_edited_by);
+        if self.last_edited_by.is_some() {
+            dbg.field("last_edited_by", &self.last_edited_by);
+        }
 
         if self.additional_information.is_some() {
             dbg.field("additional_information", &self.additional_information);
@@ -184,3 +362,174 @@ impl std::fmt::Debug for Item {
         dbg.finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+    use crate::FieldType;
+
+    fn item() -> Item {
+        fixtures::item(serde_json::json!({
+            "title": "Secrets Automation Item",
+            "vault": {"id": "ftz4pm2xxwmwrsd7rjqn7grzfz", "name": "dev"},
+            "category": "LOGIN",
+            "version": 2,
+            "sections": [
+                {"id": "95cdbc3b-7742-47ec-9056-44d6af82d562", "label": "Security Questions"},
+                {"id": "linked items", "label": "Related Items"},
+                {"id": "7bbcd5ff-1ff8-4e6f-9e9e-7a3d2fd0b1a6", "label": "Backup"}
+            ],
+            "fields": [
+                {"id": "username", "type": "STRING", "purpose": "USERNAME", "label": "username", "value": "wendy"},
+                {
+                    "id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password",
+                    "value": "hLDegPkuMQqyQiyDZqRdWGoojiN5KYQtXuA0wBDe9z3Caj6FQGHpbGu",
+                    "entropy": 189.78359985351562,
+                    "reference": "op://dev/Secrets Automation Item/password"
+                },
+                {
+                    "id": "boot3vsxwhuht6g7cmcx4m6rcm",
+                    "section": {"id": "95cdbc3b-7742-47ec-9056-44d6af82d562"},
+                    "type": "CONCEALED", "label": "Recovery Key", "value": "first"
+                },
+                {
+                    "id": "axwtgyjrvwfp5ij7mtkw2zvijy",
+                    "section": {"id": "7bbcd5ff-1ff8-4e6f-9e9e-7a3d2fd0b1a6"},
+                    "type": "CONCEALED", "label": "Recovery Key", "value": "second",
+                    "recipe": {"length": 24, "characterSets": ["LETTERS", "DIGITS"]}
+                }
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_field_attributes() {
+        let item = item();
+        let field = item.get_field("Backup", "Recovery Key").expect("no field");
+        assert_eq!(field.recipe.as_ref().map(|r| r.length), Some(24));
+        let password = item
+            .fields
+            .iter()
+            .flatten()
+            .find(|field| field.id == "password")
+            .unwrap();
+        assert!(password.entropy.is_some());
+        assert!(password.reference.is_some());
+    }
+
+    #[test]
+    fn test_get_field_by_section() {
+        let item = item();
+        let first = item
+            .get_field("Security Questions", "Recovery Key")
+            .expect("no field");
+        assert_eq!(first.value.as_ref().unwrap().inner(), "first");
+        let second = item
+            .get_field("7bbcd5ff-1ff8-4e6f-9e9e-7a3d2fd0b1a6", "Recovery Key")
+            .expect("no field");
+        assert_eq!(second.value.as_ref().unwrap().inner(), "second");
+    }
+
+    #[test]
+    fn test_ambiguous_label() {
+        let item = item();
+        assert_eq!(
+            item.get_field_by_label("username").unwrap().inner(),
+            "wendy"
+        );
+        assert!(matches!(
+            item.get_field_by_label("Recovery Key"),
+            Err(Error::AmbiguousField(label)) if label == "Recovery Key"
+        ));
+        assert!(matches!(
+            item.get_field_by_label("missing"),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_fields_by_section() {
+        let item = item();
+        let groups: Vec<(Option<&str>, usize)> = item
+            .fields_by_section()
+            .map(|(section, fields)| (section.map(|s| s.id.as_str()), fields.len()))
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                (None, 2),
+                (Some("95cdbc3b-7742-47ec-9056-44d6af82d562"), 1),
+                (Some("7bbcd5ff-1ff8-4e6f-9e9e-7a3d2fd0b1a6"), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fields_by_section_unknown_section() {
+        let item = fixtures::item(serde_json::json!({
+            "sections": [{"id": "known"}, {"id": "known"}],
+            "fields": [
+                {"id": "a", "type": "STRING", "label": "a", "section": {"id": "known"}},
+                {"id": "b", "type": "STRING", "label": "b", "section": {"id": "missing"}},
+                {"id": "c", "type": "STRING", "label": "c"}
+            ]
+        }));
+        let groups: Vec<(Option<&str>, Vec<&str>)> = item
+            .fields_by_section()
+            .map(|(section, fields)| {
+                (
+                    section.map(|s| s.id.as_str()),
+                    fields.iter().map(|f| f.id.as_str()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![(None, vec!["b", "c"]), (Some("known"), vec!["a"])]
+        );
+    }
+
+    #[test]
+    fn test_fixtures() {
+        let identity: Item =
+            serde_json::from_str(include_str!("../../tests/fixtures/identity_item.json"))
+                .expect("failed to parse identity");
+        assert_eq!(identity.category, Category::Identity);
+        let types: Vec<FieldType> = identity
+            .fields
+            .iter()
+            .flatten()
+            .map(|field| field._type.clone())
+            .collect();
+        assert!(types.contains(&FieldType::Address));
+        assert!(types.contains(&FieldType::Phone));
+        assert!(types.contains(&FieldType::Gender));
+
+        let ssh_key: Item =
+            serde_json::from_str(include_str!("../../tests/fixtures/ssh_key_item.json"))
+                .expect("failed to parse ssh key");
+        assert_eq!(
+            ssh_key.as_ssh_key().unwrap().key_type.as_deref(),
+            Some("ed25519")
+        );
+
+        let wallet: Item =
+            serde_json::from_str(include_str!("../../tests/fixtures/crypto_wallet_item.json"))
+                .expect("failed to parse crypto wallet");
+        assert_eq!(wallet.category, Category::CryptoWallet);
+        let field = wallet.get_field_by_label("from the future");
+        assert!(field.is_ok());
+        let json = serde_json::to_value(&wallet).unwrap();
+        assert_eq!(json["fields"][2]["type"], "HOLOGRAPHIC_SIGNATURE");
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let item = item();
+        let debug = format!("{:?} {:#?}", item, item);
+        assert!(!debug.contains("hLDegPkuMQqyQiyDZqRdWGoojiN5KYQtXuA0wBDe9z3Caj6FQGHpbGu"));
+        assert!(!debug.contains("wendy"));
+        assert!(debug.contains("Recovery Key"));
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{Category, Item, Url, Vault};
+
+/// Item summary, as returned by the item list endpoint.  The list endpoint
+/// never returns fields, files or sections; fetch the full [Item](super::Item)
+/// for those.
+///
+/// ```json
+///{
+///    "id": "2fcbqwe9ndg175zg2dzwftvkpa",
+///    "title": "Secrets Automation Item",
+///    "tags": [
+///        "connect",
+///        "🐧"
+///    ],
+///    "vault": {
+///        "id": "ftz4pm2xxwmwrsd7rjqn7grzfz"
+///    },
+///    "category": "LOGIN",
+///    "version": 2,
+///    "createdAt": "2021-04-10T17:20:05.98944527Z",
+///    "updatedAt": "2021-04-13T17:20:05.989445411Z"
+///}
+/// ```
+#[derive(Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemOverview {
+    /// The UUID of the item.
+    pub id: String,
+    /// The title of the item.
+    pub title: String,
+    /// An object containing an id property whose value is the UUID of the vault the item is in.
+    pub vault: Vault,
+    /// The category of the item.
+    pub category: Category,
+    /// Array of [Url] objects containing URLs for the item
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub urls: Option<Vec<Url>>,
+    /// Whether the item is marked as a favorite.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favorite: Option<bool>,
+    /// An array of strings of the tags assigned to the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
+    /// The version of the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<u32>,
+    /// The state of the item, such as "ARCHIVED".  Absent for active items.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    /// Date and time when the item was created.
+    pub created_at: DateTime<Utc>,
+    /// Date and time when the item was last changed.
+    pub updated_at: DateTime<Utc>,
+    /// UUID of the account that last changed the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_edited_by: Option<String>,
+}
+
+impl From<&Item> for ItemOverview {
+    fn from(item: &Item) -> Self {
+        Self {
+            id: item.id.clone(),
+            title: item.title.clone(),
+            vault: item.vault.clone(),
+            category: item.category.clone(),
+            urls: item.urls.clone(),
+            favorite: item.favorite,
+            tags: item.tags.clone(),
+            version: Some(item.version),
+            state: item.state.clone(),
+            created_at: item.created_at,
+            updated_at: item.updated_at,
+            last_edited_by: item.last_edited_by.clone(),
+        }
+    }
+}
+
+impl std::fmt::Debug for ItemOverview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut dbg = f.debug_struct("ItemOverview");
+        dbg.field("id", &self.id);
+        dbg.field("title", &self.title);
+        dbg.field("vault", &self.vault);
+        dbg.field("category", &self.category);
+        if self.urls.is_some() {
+            dbg.field("urls", &self.urls);
+        }
+        if self.favorite.is_some() {
+            dbg.field("favorite", &self.favorite);
+        }
+        if self.tags.is_some() {
+            dbg.field("tags", &self.tags);
+        }
+        if self.version.is_some() {
+            dbg.field("version", &self.version);
+        }
+        if self.state.is_some() {
+            dbg.field("state", &self.state);
+        }
+        dbg.field("created_at", &self.created_at);
+        dbg.field("updated_at", &self.updated_at);
+        if self.last_edited_by.is_some() {
+            dbg.field("last_edited_by", &self.last_edited_by);
+        }
+        dbg.finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn test_missing_last_edited_by() {
+        let mut first = fixtures::item_json(serde_json::json!({}));
+        first.as_object_mut().unwrap().remove("lastEditedBy");
+        let mut second = fixtures::item_json(serde_json::json!({}));
+        second.as_object_mut().unwrap().remove("version");
+        let items: Vec<ItemOverview> = serde_json::from_value(serde_json::json!([first, second]))
+            .expect("failed to parse items");
+        assert_eq!(items.len(), 2);
+        assert!(items[0].last_edited_by.is_none());
+        assert!(items[1].version.is_none());
+    }
+}
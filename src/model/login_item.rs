@@ -0,0 +1,110 @@
+use super::{Category, FieldValue, Item};
+use crate::{Error, Result};
+
+/// Typed view of a LOGIN item.
+///
+/// Both the username and password are required.
+#[derive(Debug, Clone)]
+pub struct LoginItem {
+    pub username: String,
+    pub password: FieldValue,
+    /// The href of the primary URL, or the first URL if none is primary.
+    pub url: Option<String>,
+}
+
+impl TryFrom<&Item> for LoginItem {
+    type Error = Error;
+
+    fn try_from(item: &Item) -> Result<Self> {
+        item.expect_category(Category::Login)?;
+        let url = item.urls.as_ref().and_then(|urls| {
+            urls.iter()
+                .find(|url| url.primary)
+                .or(urls.first())
+                .map(|url| url.href.clone())
+        });
+        Ok(Self {
+            username: item.require_value(&["username"])?.inner().to_owned(),
+            password: item.require_value(&["password"])?,
+            url,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn login_item() -> Item {
+        fixtures::item(serde_json::json!({
+            "title": "GitHub",
+            "category": "LOGIN",
+            "fields": [
+                {"id": "username", "type": "STRING", "purpose": "USERNAME", "label": "username", "value": "octocat"},
+                {"id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password", "value": "p4ss-w0rd-Exa!mple"},
+                {"id": "notesPlain", "type": "STRING", "purpose": "NOTES", "label": "notesPlain"}
+            ],
+            "urls": [
+                {"label": "website", "primary": true, "href": "https://github.com/login"},
+                {"href": "https://github.com"}
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_login() {
+        let login = LoginItem::try_from(&login_item()).unwrap();
+        assert_eq!(login.username, "octocat");
+        assert_eq!(login.password.expose_secret(), "p4ss-w0rd-Exa!mple");
+        assert_eq!(login.url.as_deref(), Some("https://github.com/login"));
+
+        // Without a primary URL, the first one.
+        let mut item = login_item();
+        item.urls.as_mut().unwrap().reverse();
+        item.urls.as_mut().unwrap()[1].primary = false;
+        let login = LoginItem::try_from(&item).unwrap();
+        assert_eq!(login.url.as_deref(), Some("https://github.com"));
+
+        item.urls = None;
+        assert!(LoginItem::try_from(&item).unwrap().url.is_none());
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let mut item = login_item();
+        item.fields
+            .as_mut()
+            .unwrap()
+            .retain(|field| field.id != "password");
+        assert!(matches!(
+            LoginItem::try_from(&item),
+            Err(Error::MissingField(field)) if field == "password"
+        ));
+
+        // An empty value is as good as none.
+        let mut item = login_item();
+        for field in item.fields.iter_mut().flatten() {
+            if field.id == "username" {
+                field.value = Some(FieldValue::new(""));
+            }
+        }
+        assert!(matches!(
+            LoginItem::try_from(&item),
+            Err(Error::MissingField(field)) if field == "username"
+        ));
+    }
+
+    #[test]
+    fn test_wrong_category() {
+        let mut item = login_item();
+        item.category = Category::Password;
+        assert!(matches!(
+            LoginItem::try_from(&item),
+            Err(Error::CategoryMismatch {
+                expected: Category::Login,
+                found: Category::Password
+            })
+        ));
+    }
+}
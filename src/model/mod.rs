@@ -1,5 +1,9 @@
+pub use api_credential_item::*;
 pub use category::*;
 pub use character_set::*;
+pub use credit_card_item::*;
+pub use database_item::*;
+pub use database_type::*;
 pub use field::*;
 pub use field_purpose::*;
 pub use field_type::*;
@@ -7,13 +11,24 @@ pub use field_value::*;
 pub use file::*;
 pub use generator_recipe::*;
 pub use item::*;
+pub use item_overview::*;
+pub use login_item::*;
+pub use month_year::*;
+pub use patch_operation::*;
+pub use secret_reference::*;
 pub use section::*;
+pub use server_item::*;
+pub use ssh_key_item::*;
 pub use url::*;
 pub use vault::*;
 pub use vault_type::*;
 
+pub mod api_credential_item;
 pub mod category;
 pub mod character_set;
+pub mod credit_card_item;
+pub mod database_item;
+pub mod database_type;
 pub mod field;
 pub mod field_purpose;
 pub mod field_type;
@@ -21,7 +36,17 @@ pub mod field_value;
 pub mod file;
 pub mod generator_recipe;
 pub mod item;
+pub mod item_overview;
+pub mod login_item;
+pub mod month_year;
+pub mod patch_operation;
+pub mod secret_reference;
 pub mod section;
+pub mod server_item;
+pub mod ssh_key_item;
 pub mod url;
 pub mod vault;
 pub mod vault_type;
+
+#[cfg(test)]
+mod conformance;
@@ -0,0 +1,113 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Month and year, as used by MONTH_YEAR fields such as a credit card expiry.
+///
+/// Connect formats these as `YYYYMM` or `YYYY/MM`.
+/// ```rust
+/// use op_connect_rs::MonthYear;
+///
+/// let expiry: MonthYear = "202712".parse().unwrap();
+/// assert_eq!(expiry, MonthYear::new(2027, 12).unwrap());
+/// assert_eq!(expiry.to_string(), "2027/12");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MonthYear {
+    year: u16,
+    month: u8,
+}
+
+impl MonthYear {
+    /// Create a MonthYear.  Returns None if month is not 1 - 12.
+    pub fn new(year: u16, month: u8) -> Option<Self> {
+        if (1..=12).contains(&month) {
+            Some(Self { year, month })
+        } else {
+            None
+        }
+    }
+
+    pub fn year(&self) -> u16 {
+        self.year
+    }
+
+    pub fn month(&self) -> u8 {
+        self.month
+    }
+
+    /// The last day of the month, which is when a card with this expiry
+    /// stops working.
+    pub fn last_day(&self) -> NaiveDate {
+        let (year, month) = if self.month == 12 {
+            (self.year as i32 + 1, 1)
+        } else {
+            (self.year as i32, self.month as u32 + 1)
+        };
+        NaiveDate::from_ymd_opt(year, month, 1)
+            .and_then(|first| first.pred_opt())
+            .expect("valid month")
+    }
+}
+
+impl FromStr for MonthYear {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (year, month) = match s.split_once('/') {
+            Some((year, month)) => (year, month),
+            None if s.len() == 6 && s.is_char_boundary(4) => s.split_at(4),
+            None => return Err(Error::InvalidField("month_year".to_owned())),
+        };
+        let year = year
+            .parse::<u16>()
+            .map_err(|_| Error::InvalidField("month_year".to_owned()))?;
+        let month = month
+            .parse::<u8>()
+            .map_err(|_| Error::InvalidField("month_year".to_owned()))?;
+        Self::new(year, month).ok_or(Error::InvalidField("month_year".to_owned()))
+    }
+}
+
+impl fmt::Display for MonthYear {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}/{:02}", self.year, self.month)
+    }
+}
+
+impl Serialize for MonthYear {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:04}{:02}", self.year, self.month))
+    }
+}
+
+impl<'de> Deserialize<'de> for MonthYear {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "202501".parse::<MonthYear>().unwrap(),
+            MonthYear::new(2025, 1).unwrap()
+        );
+        assert_eq!(
+            "2025/11".parse::<MonthYear>().unwrap(),
+            MonthYear::new(2025, 11).unwrap()
+        );
+        assert!("202513".parse::<MonthYear>().is_err());
+        assert!("2025".parse::<MonthYear>().is_err());
+    }
+}
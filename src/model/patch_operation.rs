@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use super::FieldValue;
+
+/// Item PATCH operation, following RFC 6902 JSON Patch.
+///
+/// ```json
+/// {
+///    "op": "replace",
+///    "path": "/fields/password/value",
+///    "value": "new password"
+/// }
+/// ```
+///
+/// Values are held as [FieldValue]s, so they are redacted and zeroized like
+/// any other secret.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PatchOperation {
+    pub op: PatchOp,
+    /// Path into the item, such as `/title` or `/fields/{field id}/value`.
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<FieldValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PatchOp {
+    Add,
+    Remove,
+    Replace,
+}
+
+impl PatchOperation {
+    /// Replace the value of a field.
+    pub fn replace_field_value(field_id: &str, value: FieldValue) -> Self {
+        Self {
+            op: PatchOp::Replace,
+            path: format!("/fields/{}/value", field_id),
+            value: Some(value),
+        }
+    }
+}
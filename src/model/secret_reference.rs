@@ -0,0 +1,143 @@
+use std::fmt;
+use std::str::FromStr;
+
+use percent_encoding::percent_decode_str;
+
+use super::{Field, Item, ItemOverview, Vault};
+use crate::model::item::unique_field;
+use crate::{Error, Result};
+
+/// A secret reference, `op://vault/item/[section/]field`.
+///
+/// Each part may be a name or an id, and may be percent-encoded.
+/// ```rust
+/// use op_connect_rs::SecretReference;
+///
+/// let reference: SecretReference = "op://dev/Postgres/admin%20password".parse().unwrap();
+/// assert_eq!(reference.item, "Postgres");
+/// assert_eq!(reference.field, "admin password");
+/// assert!(reference.section.is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SecretReference {
+    pub vault: String,
+    pub item: String,
+    pub section: Option<String>,
+    pub field: String,
+}
+
+impl SecretReference {
+    pub fn matches_vault(&self, vault: &Vault) -> bool {
+        vault.id == self.vault || vault.name == self.vault
+    }
+
+    pub fn matches_item(&self, item: &ItemOverview) -> bool {
+        item.id == self.item || item.title == self.item
+    }
+
+    /// The referenced field of the item, matched on id or label.
+    pub fn field<'a>(&self, item: &'a Item) -> Result<&'a Field> {
+        let section_ids: Option<Vec<&str>> = self.section.as_ref().map(|section| {
+            item.sections
+                .iter()
+                .flatten()
+                .filter(|s| &s.id == section || s.label.as_ref() == Some(section))
+                .map(|s| s.id.as_str())
+                .collect()
+        });
+        unique_field(
+            item.fields.iter().flatten().filter(|field| {
+                (field.id == self.field || field.label == self.field)
+                    && section_ids
+                        .as_ref()
+                        .is_none_or(|ids| ids.iter().any(|id| field.in_section(id)))
+            }),
+            &self.field,
+        )
+    }
+}
+
+impl FromStr for SecretReference {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidReference(s.to_owned());
+        let path = s.strip_prefix("op://").ok_or_else(invalid)?;
+        let parts = path
+            .split('/')
+            .map(|part| {
+                percent_decode_str(part)
+                    .decode_utf8()
+                    .ok()
+                    .filter(|part| !part.is_empty())
+                    .map(|part| part.into_owned())
+                    .ok_or_else(invalid)
+            })
+            .collect::<Result<Vec<String>>>()?;
+        match <[String; 3]>::try_from(parts) {
+            Ok([vault, item, field]) => Ok(Self {
+                vault,
+                item,
+                section: None,
+                field,
+            }),
+            Err(parts) => match <[String; 4]>::try_from(parts) {
+                Ok([vault, item, section, field]) => Ok(Self {
+                    vault,
+                    item,
+                    section: Some(section),
+                    field,
+                }),
+                Err(_) => Err(invalid()),
+            },
+        }
+    }
+}
+
+impl fmt::Display for SecretReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "op://{}/{}/", self.vault, self.item)?;
+        if let Some(section) = &self.section {
+            write!(f, "{}/", section)?;
+        }
+        write!(f, "{}", self.field)
+    }
+}
+
+/// The only vault or item matching a reference.
+pub(crate) fn unique_match<T>(
+    mut matches: impl Iterator<Item = T>,
+    reference: &SecretReference,
+) -> Result<T> {
+    let found = matches.next().ok_or(Error::NotFound)?;
+    if matches.next().is_some() {
+        return Err(Error::InvalidReference(format!(
+            "{} matches more than one vault or item",
+            reference
+        )));
+    }
+    Ok(found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let reference: SecretReference = "op://dev/db/Admin/password".parse().unwrap();
+        assert_eq!(reference.section.as_deref(), Some("Admin"));
+        assert_eq!(reference.to_string(), "op://dev/db/Admin/password");
+        for invalid in [
+            "dev/db/password",
+            "op://dev/db",
+            "op://dev//password",
+            "op://a/b/c/d/e",
+        ] {
+            assert!(matches!(
+                invalid.parse::<SecretReference>(),
+                Err(Error::InvalidReference(_))
+            ));
+        }
+    }
+}
@@ -14,6 +14,7 @@ pub struct Section {
     /// A unique identifier for the section.
     pub id: String,
     /// The label for the section.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub label: Option<String>,
 }
 
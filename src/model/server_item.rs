@@ -0,0 +1,95 @@
+use super::{Category, FieldValue, Item};
+use crate::{Error, Result};
+
+/// Typed view of a SERVER item.
+///
+/// The URL is required.
+#[derive(Debug, Clone)]
+pub struct ServerItem {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<FieldValue>,
+    pub admin_console_url: Option<String>,
+    pub admin_console_username: Option<String>,
+    pub admin_console_password: Option<FieldValue>,
+}
+
+impl TryFrom<&Item> for ServerItem {
+    type Error = Error;
+
+    fn try_from(item: &Item) -> Result<Self> {
+        item.expect_category(Category::Server)?;
+        Ok(Self {
+            url: item.require_value(&["url", "URL"])?.inner().to_owned(),
+            username: item.find_string(&["username"]),
+            password: item.find_value(&["password"]),
+            admin_console_url: item.find_string(&["admin_console_url"]),
+            admin_console_username: item.find_string(&["admin_console_username"]),
+            admin_console_password: item.find_value(&["admin_console_password"]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn server_item() -> Item {
+        fixtures::item(serde_json::json!({
+            "title": "bastion",
+            "category": "SERVER",
+            "fields": [
+                {"id": "url", "type": "STRING", "label": "URL", "value": "ssh://bastion.example.com"},
+                {"id": "username", "type": "STRING", "label": "username", "value": "ops"},
+                {"id": "password", "type": "CONCEALED", "label": "password", "value": "Tr0ub4dor&3"}
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_server() {
+        let server = ServerItem::try_from(&server_item()).unwrap();
+        assert_eq!(server.url, "ssh://bastion.example.com");
+        assert_eq!(server.username.as_deref(), Some("ops"));
+        assert_eq!(
+            server.password.as_ref().map(|v| v.expose_secret()),
+            Some("Tr0ub4dor&3")
+        );
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let mut item = server_item();
+        item.fields
+            .as_mut()
+            .unwrap()
+            .retain(|field| field.id != "url");
+        assert!(matches!(
+            ServerItem::try_from(&item),
+            Err(Error::MissingField(field)) if field == "url"
+        ));
+
+        // Only the URL is required.
+        let mut item = server_item();
+        item.fields
+            .as_mut()
+            .unwrap()
+            .retain(|field| field.id == "url");
+        let server = ServerItem::try_from(&item).unwrap();
+        assert!(server.username.is_none() && server.password.is_none());
+    }
+
+    #[test]
+    fn test_wrong_category() {
+        let mut item = server_item();
+        item.category = Category::Database;
+        assert!(matches!(
+            ServerItem::try_from(&item),
+            Err(Error::CategoryMismatch {
+                expected: Category::Server,
+                ..
+            })
+        ));
+    }
+}
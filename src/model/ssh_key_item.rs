@@ -0,0 +1,28 @@
+use super::{Category, FieldValue, Item};
+use crate::{Error, Result};
+
+/// Typed view of an SSH_KEY item.
+///
+/// The private key is required.
+#[derive(Debug, Clone)]
+pub struct SshKeyItem {
+    pub private_key: FieldValue,
+    pub public_key: Option<String>,
+    pub fingerprint: Option<String>,
+    /// The key type, such as "ed25519" or "rsa".
+    pub key_type: Option<String>,
+}
+
+impl TryFrom<&Item> for SshKeyItem {
+    type Error = Error;
+
+    fn try_from(item: &Item) -> Result<Self> {
+        item.expect_category(Category::SshKey)?;
+        Ok(Self {
+            private_key: item.require_value(&["private_key", "private key"])?,
+            public_key: item.find_string(&["public_key", "public key"]),
+            fingerprint: item.find_string(&["fingerprint"]),
+            key_type: item.find_string(&["key_type", "key type"]),
+        })
+    }
+}
@@ -12,8 +12,11 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct Url {
     /// The label for the URL.
-    pub label: String,
-    /// Whether this is the primary URL for the item.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Whether this is the primary URL for the item.  Connect leaves it out
+    /// unless it is set.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub primary: bool,
     /// The address.
     pub href: String,
@@ -22,22 +22,30 @@ use super::VaultType;
 pub struct Vault {
     /// The UUID of the vault.
     pub id: String,
-    /// The name of the vault.
+    /// The name of the vault.  Empty when the vault is embedded in an item,
+    /// which only carries the id.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
     /// The description for the vault.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
     /// The version of the vault metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub attribute_version: Option<u32>,
     /// The version of the vault contents.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub content_version: Option<u32>,
     /// Number of active items in the vault.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub items: Option<u32>,
     /// The type of vault.
-    #[serde(rename = "type")]
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
     pub _type: Option<VaultType>,
     /// Date and time when the vault was created.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub created_at: Option<DateTime<Utc>>,
     /// Date and time when the vault or its contents were last changed.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub updated_at: Option<DateTime<Utc>>,
 }
 
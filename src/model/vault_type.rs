@@ -9,4 +9,9 @@ pub enum VaultType {
     Personal,
     /// A vault created by a user.
     UserCreated,
+    /// A vault used to transfer items between accounts.
+    Transfer,
+    /// A vault type this crate does not know about.
+    #[serde(untagged)]
+    Unknown(String),
 }
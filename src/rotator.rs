@@ -0,0 +1,343 @@
+//! Password rotation.
+//!
+//! A [Rotator] generates a new value for a concealed field, hands it to an
+//! `apply` hook to set it on the target system (for example `ALTER USER` on
+//! a database), then writes it to the item with a PATCH.  If the vault write
+//! fails, the `rollback` hook is called with the old value.  Once both sides
+//! have the new value, the optional `commit` hook runs, for example to drop
+//! sessions that still use the old password.
+//!
+//! The item's `version` is checked against Connect just before the write, so
+//! a rotation fails with [Error::VersionConflict] on a change made since the
+//! item was read.  Connect does not check versions on PATCH, so a change made
+//! between that check and the write is only logged.  If the `commit` hook
+//! fails, [Error::CommitFailed] carries the completed rotation.
+//! ```rust,ignore
+//! let rotation = Rotator::new(recipe)
+//!     .on_apply(|password| async move { alter_user(&password).await })
+//!     .on_rollback(|password| async move { alter_user(&password).await })
+//!     .rotate(&client, &item, "password")
+//!     .await?;
+//! ```
+#[cfg(not(feature = "blocking"))]
+use std::{future::Future, pin::Pin};
+
+use crate::{
+    generate, Error, FieldType, FieldValue, GeneratorRecipe, Item, OpClient, PatchOperation, Result,
+};
+
+/// Error returned by a rotation hook.
+pub type HookError = Box<dyn std::error::Error + Send + Sync>;
+
+#[cfg(not(feature = "blocking"))]
+type Hook = Box<
+    dyn Fn(FieldValue) -> Pin<Box<dyn Future<Output = std::result::Result<(), HookError>> + Send>>
+        + Send
+        + Sync,
+>;
+#[cfg(feature = "blocking")]
+type Hook = Box<dyn Fn(FieldValue) -> std::result::Result<(), HookError> + Send + Sync>;
+
+/// A completed rotation.
+#[derive(Debug, Clone)]
+pub struct Rotation {
+    /// The item as updated by Connect.
+    pub item: Item,
+    /// Entropy of the new value, in bits.
+    pub entropy: f64,
+}
+
+pub struct Rotator {
+    recipe: GeneratorRecipe,
+    apply: Option<Hook>,
+    commit: Option<Hook>,
+    rollback: Option<Hook>,
+}
+
+impl Rotator {
+    pub fn new(recipe: GeneratorRecipe) -> Self {
+        Self {
+            recipe,
+            apply: None,
+            commit: None,
+            rollback: None,
+        }
+    }
+}
+
+#[cfg(not(feature = "blocking"))]
+fn hook<F, Fut, E>(f: F) -> Hook
+where
+    F: Fn(FieldValue) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = std::result::Result<(), E>> + Send + 'static,
+    E: Into<HookError>,
+{
+    Box::new(move |value| {
+        let future = f(value);
+        Box::pin(async move { future.await.map_err(Into::into) })
+    })
+}
+
+#[cfg(not(feature = "blocking"))]
+impl Rotator {
+    /// Set the new value on the target system.
+    pub fn on_apply<F, Fut, E>(self, f: F) -> Self
+    where
+        F: Fn(FieldValue) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), E>> + Send + 'static,
+        E: Into<HookError>,
+    {
+        Self {
+            apply: Some(hook(f)),
+            ..self
+        }
+    }
+
+    /// Called with the new value once both the target and the vault have it.
+    pub fn on_commit<F, Fut, E>(self, f: F) -> Self
+    where
+        F: Fn(FieldValue) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), E>> + Send + 'static,
+        E: Into<HookError>,
+    {
+        Self {
+            commit: Some(hook(f)),
+            ..self
+        }
+    }
+
+    /// Restore the old value on the target system after a failed vault write.
+    pub fn on_rollback<F, Fut, E>(self, f: F) -> Self
+    where
+        F: Fn(FieldValue) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = std::result::Result<(), E>> + Send + 'static,
+        E: Into<HookError>,
+    {
+        Self {
+            rollback: Some(hook(f)),
+            ..self
+        }
+    }
+
+    /// Rotate the value of a concealed field.
+    pub async fn rotate(&self, client: &OpClient, item: &Item, field_id: &str) -> Result<Rotation> {
+        let old = rotated_field_value(item, field_id)?;
+        let current = client.get_item_detail(&item.vault, &item.id).await?;
+        check_version(item, &current)?;
+        let generated = generate(&self.recipe)?;
+        let new = generated.value;
+
+        if let Some(apply) = &self.apply {
+            apply(new.clone()).await.map_err(Error::Hook)?;
+        }
+
+        let operations = [PatchOperation::replace_field_value(field_id, new.clone())];
+        let updated = match client.patch_item(&current, &operations).await {
+            Ok(updated) => updated,
+            Err(write) => {
+                return Err(match &self.rollback {
+                    Some(rollback) => match rollback(old).await {
+                        Ok(()) => write,
+                        Err(rollback) => rollback_failed(write, rollback, new),
+                    },
+                    None => write,
+                })
+            }
+        };
+        check_written(&current, &updated);
+
+        let rotation = Rotation {
+            item: updated,
+            entropy: generated.entropy,
+        };
+        if let Some(commit) = &self.commit {
+            if let Err(source) = commit(new).await {
+                return Err(commit_failed(rotation, source));
+            }
+        }
+        Ok(rotation)
+    }
+}
+
+#[cfg(feature = "blocking")]
+impl Rotator {
+    /// Set the new value on the target system.
+    pub fn on_apply<F, E>(self, f: F) -> Self
+    where
+        F: Fn(FieldValue) -> std::result::Result<(), E> + Send + Sync + 'static,
+        E: Into<HookError>,
+    {
+        Self {
+            apply: Some(Box::new(move |value| f(value).map_err(Into::into))),
+            ..self
+        }
+    }
+
+    /// Called with the new value once both the target and the vault have it.
+    pub fn on_commit<F, E>(self, f: F) -> Self
+    where
+        F: Fn(FieldValue) -> std::result::Result<(), E> + Send + Sync + 'static,
+        E: Into<HookError>,
+    {
+        Self {
+            commit: Some(Box::new(move |value| f(value).map_err(Into::into))),
+            ..self
+        }
+    }
+
+    /// Restore the old value on the target system after a failed vault write.
+    pub fn on_rollback<F, E>(self, f: F) -> Self
+    where
+        F: Fn(FieldValue) -> std::result::Result<(), E> + Send + Sync + 'static,
+        E: Into<HookError>,
+    {
+        Self {
+            rollback: Some(Box::new(move |value| f(value).map_err(Into::into))),
+            ..self
+        }
+    }
+
+    /// Rotate the value of a concealed field.
+    pub fn rotate(&self, client: &OpClient, item: &Item, field_id: &str) -> Result<Rotation> {
+        let old = rotated_field_value(item, field_id)?;
+        let current = client.get_item_detail(&item.vault, &item.id)?;
+        check_version(item, &current)?;
+        let generated = generate(&self.recipe)?;
+        let new = generated.value;
+
+        if let Some(apply) = &self.apply {
+            apply(new.clone()).map_err(Error::Hook)?;
+        }
+
+        let operations = [PatchOperation::replace_field_value(field_id, new.clone())];
+        let updated = match client.patch_item(&current, &operations) {
+            Ok(updated) => updated,
+            Err(write) => {
+                return Err(match &self.rollback {
+                    Some(rollback) => match rollback(old) {
+                        Ok(()) => write,
+                        Err(rollback) => rollback_failed(write, rollback, new),
+                    },
+                    None => write,
+                })
+            }
+        };
+        check_written(&current, &updated);
+
+        let rotation = Rotation {
+            item: updated,
+            entropy: generated.entropy,
+        };
+        if let Some(commit) = &self.commit {
+            if let Err(source) = commit(new) {
+                return Err(commit_failed(rotation, source));
+            }
+        }
+        Ok(rotation)
+    }
+}
+
+/// The current value of the field being rotated, which must be concealed and
+/// have a value to roll back to.
+fn rotated_field_value(item: &Item, field_id: &str) -> Result<FieldValue> {
+    let field = item
+        .fields
+        .iter()
+        .flatten()
+        .find(|field| field.id == field_id)
+        .ok_or_else(|| Error::MissingField(field_id.to_owned()))?;
+    if field._type != FieldType::Concealed {
+        return Err(Error::InvalidField(field_id.to_owned()));
+    }
+    field
+        .value
+        .clone()
+        .ok_or_else(|| Error::MissingField(field_id.to_owned()))
+}
+
+fn check_version(item: &Item, current: &Item) -> Result<()> {
+    if item.version == current.version {
+        Ok(())
+    } else {
+        Err(Error::VersionConflict {
+            expected: item.version,
+            found: current.version,
+        })
+    }
+}
+
+/// Connect does not check versions on PATCH, so a concurrent write between
+/// the version check and our write shows up as a skipped version.
+fn check_written(current: &Item, updated: &Item) {
+    if updated.version != current.version + 1 {
+        log::warn!(
+            "item {} went from version {} to {} during rotation",
+            current.id,
+            current.version,
+            updated.version
+        );
+    }
+}
+
+fn commit_failed(rotation: Rotation, source: HookError) -> Error {
+    Error::CommitFailed {
+        rotation: Box::new(rotation),
+        source,
+    }
+}
+
+fn rollback_failed(write: Error, rollback: HookError, new_value: FieldValue) -> Error {
+    Error::RollbackFailed {
+        write: Box::new(write),
+        rollback,
+        new_value,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn item(version: u32) -> Item {
+        fixtures::item(serde_json::json!({"version": version}))
+    }
+
+    #[test]
+    fn test_rotated_field_value() {
+        let item = item(1);
+        assert_eq!(
+            rotated_field_value(&item, "password")
+                .unwrap()
+                .expose_secret(),
+            "hunter2"
+        );
+        assert!(matches!(
+            rotated_field_value(&item, "username"),
+            Err(Error::InvalidField(_))
+        ));
+        assert!(matches!(
+            rotated_field_value(&item, "missing"),
+            Err(Error::MissingField(_))
+        ));
+
+        let mut empty = item;
+        empty.fields.as_mut().unwrap()[1].value = None;
+        assert!(matches!(
+            rotated_field_value(&empty, "password"),
+            Err(Error::MissingField(_))
+        ));
+    }
+
+    #[test]
+    fn test_check_version() {
+        assert!(check_version(&item(3), &item(3)).is_ok());
+        assert!(matches!(
+            check_version(&item(3), &item(4)),
+            Err(Error::VersionConflict {
+                expected: 3,
+                found: 4
+            })
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+//! Backing storage for [FieldValue](crate::FieldValue).
+//!
+//! By default values are kept in an ordinary heap allocated `String`.  With
+//! the `mlock` feature on unix, each value gets its own page-locked mapping
+//! surrounded by inaccessible guard pages, so it is never swapped to disk.  On
+//! Linux the mapping is also excluded from core dumps with `MADV_DONTDUMP`.
+//!
+//! Locked mappings cost at least three pages per value, so the feature is
+//! meant for services holding a modest number of credentials.
+//!
+//! Response bodies are read into `Zeroizing` buffers grown by
+//! [reserve_zeroizing], so growing one never leaves a stale copy behind.
+
+use zeroize::Zeroizing;
+
+pub(crate) use imp::SecretBuf;
+
+/// Largest `Content-Length` a response body buffer is presized to; longer
+/// bodies grow as they are read.
+pub(crate) const MAX_PRESIZE: u64 = 1 << 24;
+
+/// Make room for `additional` more bytes.  Rather than letting the `Vec`
+/// reallocate, which frees the old allocation without wiping it, the contents
+/// are copied into a new zeroizing buffer and the old one is wiped.
+pub(crate) fn reserve_zeroizing(buf: &mut Zeroizing<Vec<u8>>, additional: usize) {
+    if buf.capacity() - buf.len() >= additional {
+        return;
+    }
+    let capacity = (buf.len() + additional).max(buf.capacity() * 2);
+    let mut grown = Zeroizing::new(Vec::with_capacity(capacity));
+    grown.extend_from_slice(buf);
+    *buf = grown;
+}
+
+#[cfg(not(all(feature = "mlock", unix)))]
+mod imp {
+    use zeroize::Zeroize;
+
+    #[derive(Clone)]
+    pub(crate) struct SecretBuf(String);
+
+    impl SecretBuf {
+        pub(crate) fn from_str(value: &str) -> Self {
+            Self(String::from(value))
+        }
+
+        pub(crate) fn from_string(value: String) -> Self {
+            Self(value)
+        }
+
+        pub(crate) fn as_str(&self) -> &str {
+            &self.0
+        }
+
+        pub(crate) fn as_string(&self) -> &String {
+            &self.0
+        }
+    }
+
+    impl Zeroize for SecretBuf {
+        fn zeroize(&mut self) {
+            self.0.zeroize()
+        }
+    }
+}
+
+#[cfg(all(feature = "mlock", unix))]
+mod imp {
+    use std::alloc::{handle_alloc_error, Layout};
+    use std::ptr::{self, NonNull};
+    use std::sync::OnceLock;
+
+    use zeroize::Zeroize;
+
+    pub(crate) struct SecretBuf {
+        /// Start of the data pages, one page past the start of the mapping.
+        data: NonNull<u8>,
+        /// Size of the data pages, a multiple of the page size.
+        capacity: usize,
+        len: usize,
+        locked: bool,
+    }
+
+    // The mapping is owned exclusively by the SecretBuf, like a Box.
+    unsafe impl Send for SecretBuf {}
+    unsafe impl Sync for SecretBuf {}
+
+    fn page_size() -> usize {
+        static PAGE_SIZE: OnceLock<usize> = OnceLock::new();
+        *PAGE_SIZE.get_or_init(|| {
+            // SAFETY: sysconf has no preconditions.
+            let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+            if size > 0 {
+                size as usize
+            } else {
+                4096
+            }
+        })
+    }
+
+    impl SecretBuf {
+        fn with_len(len: usize) -> Self {
+            let page = page_size();
+            let capacity = len.max(1).div_ceil(page) * page;
+            let total = capacity + 2 * page;
+            let layout = Layout::from_size_align(total, page).expect("secret too large");
+
+            // SAFETY: an anonymous private mapping with no address hint.  The
+            // result is checked before use.
+            let base = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    total,
+                    libc::PROT_NONE,
+                    libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                    -1,
+                    0,
+                )
+            };
+            if base == libc::MAP_FAILED {
+                handle_alloc_error(layout);
+            }
+
+            // SAFETY: the data pages lie inside the mapping created above; the
+            // first and last pages stay PROT_NONE as guard pages.
+            unsafe {
+                let data = (base as *mut u8).add(page);
+                if libc::mprotect(
+                    data as *mut libc::c_void,
+                    capacity,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                ) != 0
+                {
+                    libc::munmap(base, total);
+                    handle_alloc_error(layout);
+                }
+                #[cfg(target_os = "linux")]
+                libc::madvise(base, total, libc::MADV_DONTDUMP);
+                let locked = libc::mlock(data as *const libc::c_void, capacity) == 0;
+                if !locked {
+                    log::warn!("mlock failed, secret may be swapped to disk; check RLIMIT_MEMLOCK");
+                }
+                Self {
+                    data: NonNull::new_unchecked(data),
+                    capacity,
+                    len,
+                    locked,
+                }
+            }
+        }
+
+        pub(crate) fn from_str(value: &str) -> Self {
+            let buf = Self::with_len(value.len());
+            // SAFETY: the data pages are writable and at least value.len() long.
+            unsafe {
+                ptr::copy_nonoverlapping(value.as_ptr(), buf.data.as_ptr(), value.len());
+            }
+            buf
+        }
+
+        /// Copy into locked memory, and wipe the original.
+        pub(crate) fn from_string(mut value: String) -> Self {
+            let buf = Self::from_str(&value);
+            value.zeroize();
+            buf
+        }
+
+        pub(crate) fn as_str(&self) -> &str {
+            // SAFETY: the first len bytes were copied from a str and are only
+            // ever zeroized together with len being reset.
+            unsafe {
+                let bytes = std::slice::from_raw_parts(self.data.as_ptr(), self.len);
+                std::str::from_utf8_unchecked(bytes)
+            }
+        }
+
+        fn data_mut(&mut self) -> &mut [u8] {
+            // SAFETY: the data pages are writable and capacity bytes long.
+            unsafe { std::slice::from_raw_parts_mut(self.data.as_ptr(), self.capacity) }
+        }
+    }
+
+    /// Clones are copied straight into a new locked mapping.
+    impl Clone for SecretBuf {
+        fn clone(&self) -> Self {
+            Self::from_str(self.as_str())
+        }
+    }
+
+    impl Zeroize for SecretBuf {
+        fn zeroize(&mut self) {
+            self.data_mut().zeroize();
+            self.len = 0;
+        }
+    }
+
+    impl Drop for SecretBuf {
+        fn drop(&mut self) {
+            self.data_mut().zeroize();
+            let page = page_size();
+            // SAFETY: unmaps exactly the mapping created in with_len.
+            unsafe {
+                if self.locked {
+                    libc::munlock(self.data.as_ptr() as *const libc::c_void, self.capacity);
+                }
+                let base = self.data.as_ptr().sub(page);
+                libc::munmap(base as *mut libc::c_void, self.capacity + 2 * page);
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+
+        #[test]
+        fn test_locked_clone() {
+            let secret = SecretBuf::from_str("correct horse battery staple");
+            let clone = secret.clone();
+            assert_ne!(secret.data, clone.data);
+            assert_eq!(clone.as_str(), "correct horse battery staple");
+            drop(secret);
+            assert_eq!(clone.as_str(), "correct horse battery staple");
+        }
+
+        #[test]
+        fn test_zeroize() {
+            let mut secret = SecretBuf::from_string("hunter2".to_owned());
+            secret.zeroize();
+            assert_eq!(secret.as_str(), "");
+            assert!(secret.data_mut().iter().all(|b| *b == 0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reserve_zeroizing() {
+        let mut buf = Zeroizing::new(Vec::with_capacity(4));
+        buf.extend_from_slice(b"abc");
+        reserve_zeroizing(&mut buf, 1);
+        assert_eq!(buf.capacity(), 4);
+        reserve_zeroizing(&mut buf, 2);
+        assert!(buf.capacity() >= 8);
+        assert_eq!(&buf[..], b"abc");
+    }
+}
@@ -0,0 +1,141 @@
+//! Request coalescing for the async client.
+//!
+//! Concurrent GETs for the same URL share one request, and every caller gets
+//! a copy of the response.  Requests run in their own Tokio task, so a
+//! caller that is cancelled only stops waiting: the request keeps running,
+//! and leaves the table when it completes, even if every caller gave up.  A
+//! later caller joins it while it runs, and starts a new one after.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+
+use futures::future::{BoxFuture, FutureExt, Shared};
+use reqwest::StatusCode;
+use zeroize::Zeroizing;
+
+use crate::{Error, Result};
+
+pub(crate) type Response = (StatusCode, Arc<Zeroizing<Vec<u8>>>);
+
+type InFlight = Shared<BoxFuture<'static, std::result::Result<Response, Arc<Error>>>>;
+
+#[derive(Default)]
+pub(crate) struct SingleFlight {
+    in_flight: Arc<Mutex<HashMap<String, InFlight>>>,
+}
+
+impl SingleFlight {
+    /// Join the request in flight for `key`, or start one with `request`.
+    pub async fn run<F>(&self, key: &str, request: impl FnOnce() -> F) -> Result<Response>
+    where
+        F: Future<Output = Result<Response>> + Send + 'static,
+    {
+        let shared = {
+            let mut in_flight = self.in_flight.lock().unwrap_or_else(|e| e.into_inner());
+            match in_flight.get(key) {
+                Some(shared) => shared.clone(),
+                None => {
+                    let table = self.in_flight.clone();
+                    let owned_key = key.to_owned();
+                    let future = request();
+                    let task = tokio::spawn(async move {
+                        let result = future.await.map_err(Arc::new);
+                        table
+                            .lock()
+                            .unwrap_or_else(|e| e.into_inner())
+                            .remove(&owned_key);
+                        result
+                    });
+                    let shared = async move {
+                        task.await
+                            .unwrap_or_else(|e| Err(Arc::new(Error::Io(std::io::Error::other(e)))))
+                    }
+                    .boxed()
+                    .shared();
+                    in_flight.insert(key.to_owned(), shared.clone());
+                    shared
+                }
+            }
+        };
+        // The error is only unwrapped when no other caller shares it.
+        shared
+            .await
+            .map_err(|e| Arc::try_unwrap(e).unwrap_or_else(Error::Shared))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_coalesce() {
+        let flight = SingleFlight::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (sender, receiver) = futures::channel::oneshot::channel::<()>();
+        let receiver = receiver.shared();
+
+        let request = || {
+            let calls = calls.clone();
+            let receiver = receiver.clone();
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let _ = receiver.await;
+                Ok((StatusCode::OK, Arc::new(Zeroizing::new(b"{}".to_vec()))))
+            }
+        };
+
+        // The first caller gives up, the others still get the response.
+        let mut cancelled = flight.run("vaults", request()).boxed();
+        assert!(futures::poll!(&mut cancelled).is_pending());
+        drop(cancelled);
+        let mut joined = futures::future::join(
+            flight.run("vaults", request()),
+            flight.run("vaults", request()),
+        )
+        .boxed();
+        assert!(futures::poll!(&mut joined).is_pending());
+        sender.send(()).unwrap();
+        let (a, b) = joined.await;
+        assert_eq!(a.unwrap().1.as_slice(), b"{}");
+        assert_eq!(b.unwrap().0, StatusCode::OK);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert!(flight.in_flight.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_all_callers_cancelled() {
+        let flight = SingleFlight::default();
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (sender, receiver) = futures::channel::oneshot::channel::<()>();
+        let receiver = receiver.shared();
+        let request = || {
+            let calls = calls.clone();
+            let receiver = receiver.clone();
+            move || async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                let _ = receiver.await;
+                Ok((StatusCode::OK, Arc::new(Zeroizing::new(b"{}".to_vec()))))
+            }
+        };
+
+        let mut cancelled = flight.run("vaults", request()).boxed();
+        assert!(futures::poll!(&mut cancelled).is_pending());
+        drop(cancelled);
+        sender.send(()).unwrap();
+        // The request finishes on its own and leaves the table.
+        for _ in 0..100 {
+            if flight.in_flight.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(1)).await;
+        }
+        assert!(flight.in_flight.lock().unwrap().is_empty());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // A later caller starts a new request.
+        flight.run("vaults", request()).await.unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
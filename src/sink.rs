@@ -0,0 +1,432 @@
+//! Write secrets to files.
+//!
+//! A [FileSink] writes fields, file attachments or templates from one item
+//! to paths, for example on a tmpfs, so services such as nginx or postgres
+//! can read TLS keys and passwords without a sidecar.  Files are replaced
+//! atomically, with mode 0600 unless set otherwise, and files whose content
+//! did not change are left alone.  If any file changed, the reload command is
+//! run afterwards.
+//!
+//! `FileSink::write` writes once.  `FileSink::run` writes, then watches the
+//! item and rewrites the files whenever it changes.
+//! ```rust,ignore
+//! let sink = FileSink::new(&vault, "tls")
+//!     .target(SinkTarget::file("/run/secrets/tls.key", "key.pem"))
+//!     .target(SinkTarget::template(
+//!         "/run/secrets/pgpass",
+//!         "db:5432:*:{{ username }}:{{ password }}",
+//!     ))
+//!     .with_reload_command(["nginx", "-s", "reload"]);
+//! sink.run(&client, Duration::from_secs(60)).await?;
+//! ```
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+#[cfg(any(feature = "async", feature = "blocking"))]
+use std::time::Duration;
+
+#[cfg(all(feature = "async", not(feature = "blocking")))]
+use futures::StreamExt;
+
+use zeroize::Zeroizing;
+
+use crate::model::item::unique_field;
+use crate::{atomic_file, Error, Item, OpClient, Result, Vault};
+
+/// Where the content of a file comes from.
+#[derive(Debug, Clone)]
+pub enum SinkSource {
+    /// The value of a field, matched on id or label.
+    Field(String),
+    /// A file attachment, matched on id or name.
+    File(String),
+    /// Text with `{{ field }}` placeholders, each replaced by the value of the
+    /// field with that id or label.
+    Template(String),
+}
+
+/// A file written by a [FileSink].
+#[derive(Debug, Clone)]
+pub struct SinkTarget {
+    pub path: PathBuf,
+    pub source: SinkSource,
+    /// Defaults to 0600.
+    pub mode: u32,
+    /// User and group ids to give the file to.  Needs privileges.
+    pub owner: Option<(Option<u32>, Option<u32>)>,
+}
+
+impl SinkTarget {
+    pub fn new(path: impl Into<PathBuf>, source: SinkSource) -> Self {
+        Self {
+            path: path.into(),
+            source,
+            mode: 0o600,
+            owner: None,
+        }
+    }
+
+    pub fn field(path: impl Into<PathBuf>, field: &str) -> Self {
+        Self::new(path, SinkSource::Field(field.to_owned()))
+    }
+
+    pub fn file(path: impl Into<PathBuf>, file: &str) -> Self {
+        Self::new(path, SinkSource::File(file.to_owned()))
+    }
+
+    pub fn template(path: impl Into<PathBuf>, template: &str) -> Self {
+        Self::new(path, SinkSource::Template(template.to_owned()))
+    }
+
+    pub fn with_mode(self, mode: u32) -> Self {
+        Self { mode, ..self }
+    }
+
+    pub fn with_owner(self, uid: Option<u32>, gid: Option<u32>) -> Self {
+        Self {
+            owner: Some((uid, gid)),
+            ..self
+        }
+    }
+}
+
+/// Files written from one item.
+#[derive(Debug, Clone)]
+pub struct FileSink {
+    vault: Vault,
+    item_id: String,
+    targets: Vec<SinkTarget>,
+    reload: Option<Vec<String>>,
+}
+
+impl FileSink {
+    pub fn new(vault: &Vault, item_id: &str) -> Self {
+        Self {
+            vault: vault.clone(),
+            item_id: item_id.to_owned(),
+            targets: Vec::new(),
+            reload: None,
+        }
+    }
+
+    pub fn target(mut self, target: SinkTarget) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    /// Run this command, without a shell, after files have changed.
+    pub fn with_reload_command<I, S>(self, command: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            reload: Some(command.into_iter().map(Into::into).collect()),
+            ..self
+        }
+    }
+
+    /// Fetch the item and write every target once.  Returns whether any
+    /// file changed.
+    #[cfg(not(feature = "blocking"))]
+    pub async fn write(&self, client: &OpClient) -> Result<bool> {
+        let item = client.get_item_detail(&self.vault, &self.item_id).await?;
+        self.write_item(client, &item).await
+    }
+
+    /// Fetch the item and write every target once.  Returns whether any
+    /// file changed.
+    #[cfg(feature = "blocking")]
+    pub fn write(&self, client: &OpClient) -> Result<bool> {
+        let item = client.get_item_detail(&self.vault, &self.item_id)?;
+        self.write_item(client, &item)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    async fn write_item(&self, client: &OpClient, item: &Item) -> Result<bool> {
+        let mut files = HashMap::new();
+        for file_id in self.file_ids(item)? {
+            let content = client.get_file_content(item, &file_id).await?;
+            files.insert(file_id, content);
+        }
+        // Atomic writes fsync, and the reload command waits for its process.
+        #[cfg(feature = "async")]
+        {
+            let sink = self.clone();
+            let item = item.clone();
+            tokio::task::spawn_blocking(move || sink.apply(&item, &files))
+                .await
+                .map_err(|e| Error::Io(std::io::Error::other(e)))?
+        }
+        #[cfg(not(feature = "async"))]
+        self.apply(item, &files)
+    }
+
+    #[cfg(feature = "blocking")]
+    fn write_item(&self, client: &OpClient, item: &Item) -> Result<bool> {
+        let mut files = HashMap::new();
+        for file_id in self.file_ids(item)? {
+            let content = client.get_file_content(item, &file_id)?;
+            files.insert(file_id, content);
+        }
+        self.apply(item, &files)
+    }
+
+    /// Write every target, then rewrite them whenever the item changes,
+    /// checking every `refresh`.  Only returns if the first write fails;
+    /// later failures are logged and the previous files are left in place.
+    #[cfg(all(feature = "async", not(feature = "blocking")))]
+    pub async fn run(&self, client: &OpClient, refresh: Duration) -> Result<()> {
+        let item = client.get_item_detail(&self.vault, &self.item_id).await?;
+        self.write_item(client, &item).await?;
+        let mut changes = client
+            .watch(&self.vault, std::slice::from_ref(&item))
+            .with_interval(refresh)
+            .with_jitter(refresh / 10);
+        while let Some(change) = changes.next().await {
+            let written = match change {
+                Ok(change) => self.write_item(client, &change.new).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = written {
+                log::warn!("failed to write files for item {}: {}", self.item_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write every target, then rewrite them whenever the item changes,
+    /// checking every `refresh`.  Blocks the calling thread, and only
+    /// returns if the first write fails; later failures are logged and the
+    /// previous files are left in place.
+    #[cfg(feature = "blocking")]
+    pub fn run(&self, client: &OpClient, refresh: Duration) -> Result<()> {
+        let item = client.get_item_detail(&self.vault, &self.item_id)?;
+        self.write_item(client, &item)?;
+        let changes = client
+            .watch(&self.vault, std::slice::from_ref(&item))
+            .with_interval(refresh)
+            .with_jitter(refresh / 10);
+        for change in changes {
+            let written = change.and_then(|change| self.write_item(client, &change.new));
+            if let Err(e) = written {
+                log::warn!("failed to write files for item {}: {}", self.item_id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Ids of the file attachments the targets need.
+    fn file_ids(&self, item: &Item) -> Result<Vec<String>> {
+        self.targets
+            .iter()
+            .filter_map(|target| match &target.source {
+                SinkSource::File(name) => Some(attachment_id(item, name)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Write every target from `item` and the contents of its attachments,
+    /// and run the reload command if anything changed.  Nothing is written
+    /// unless every target can be built.
+    fn apply(&self, item: &Item, files: &HashMap<String, Zeroizing<Vec<u8>>>) -> Result<bool> {
+        let contents = self
+            .targets
+            .iter()
+            .map(|target| content(target, item, files))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut changed = false;
+        for (target, content) in self.targets.iter().zip(contents) {
+            if unchanged(&target.path, &content) {
+                continue;
+            }
+            atomic_file::write(&target.path, &content, target.mode, target.owner)?;
+            changed = true;
+        }
+        if changed {
+            self.reload()?;
+        }
+        Ok(changed)
+    }
+
+    fn reload(&self) -> Result<()> {
+        let Some((program, args)) = self.reload.as_ref().and_then(|c| c.split_first()) else {
+            return Ok(());
+        };
+        let status = Command::new(program).args(args).status()?;
+        if !status.success() {
+            return Err(Error::Reload(format!("{} exited with {}", program, status)));
+        }
+        Ok(())
+    }
+}
+
+fn attachment_id(item: &Item, name: &str) -> Result<String> {
+    item.files
+        .iter()
+        .flatten()
+        .find(|file| file.id == name || file.name == name)
+        .map(|file| file.id.clone())
+        .ok_or_else(|| Error::MissingField(name.to_owned()))
+}
+
+fn field_value<'a>(item: &'a Item, name: &str) -> Result<&'a str> {
+    let field = unique_field(
+        item.fields
+            .iter()
+            .flatten()
+            .filter(|field| field.id == name || field.label == name),
+        name,
+    )
+    .map_err(|e| match e {
+        Error::NotFound => Error::MissingField(name.to_owned()),
+        e => e,
+    })?;
+    Ok(field
+        .value
+        .as_ref()
+        .map_or("", |value| value.expose_secret()))
+}
+
+fn content(
+    target: &SinkTarget,
+    item: &Item,
+    files: &HashMap<String, Zeroizing<Vec<u8>>>,
+) -> Result<Zeroizing<Vec<u8>>> {
+    match &target.source {
+        SinkSource::Field(name) => Ok(Zeroizing::new(field_value(item, name)?.as_bytes().to_vec())),
+        SinkSource::File(name) => files
+            .get(&attachment_id(item, name)?)
+            .cloned()
+            .ok_or_else(|| Error::MissingField(name.to_owned())),
+        SinkSource::Template(template) => render(template, item),
+    }
+}
+
+/// Replace each `{{ name }}` with the value of the field.  The buffer is
+/// sized up front, so it never reallocates and leaves copies behind.
+fn render(template: &str, item: &Item) -> Result<Zeroizing<Vec<u8>>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| Error::InvalidTemplate(template.to_owned()))?;
+        parts.push(&rest[..start]);
+        parts.push(field_value(item, rest[start + 2..start + end].trim())?);
+        rest = &rest[start + end + 2..];
+    }
+    parts.push(rest);
+    let mut rendered = Zeroizing::new(Vec::with_capacity(
+        parts.iter().map(|part| part.len()).sum(),
+    ));
+    for part in parts {
+        rendered.extend_from_slice(part.as_bytes());
+    }
+    Ok(rendered)
+}
+
+/// Whether the file at `path` holds `content`.  The old content is read into
+/// a zeroizing buffer of the expected size, and only if the sizes match.
+fn unchanged(path: &Path, content: &[u8]) -> bool {
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    if !file
+        .metadata()
+        .is_ok_and(|metadata| metadata.len() == content.len() as u64)
+    {
+        return false;
+    }
+    let mut existing = Zeroizing::new(vec![0; content.len()]);
+    let mut rest = [0; 1];
+    file.read_exact(&mut existing).is_ok()
+        && matches!(file.read(&mut rest), Ok(0))
+        && *existing == content
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn item(password: &str) -> Item {
+        fixtures::item(serde_json::json!({
+            "fields": [
+                {"id": "username", "type": "STRING", "label": "username", "value": "admin"},
+                {"id": "password", "type": "CONCEALED", "label": "password", "value": password}
+            ],
+            "files": [
+                {"id": "6r65pjq33banznomn7q22sj44e", "name": "ca.pem", "size": 4, "content_path": "", "content": ""}
+            ]
+        }))
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            *render("*:*:*:{{ username }}:{{password}}\n", &item("hunter2")).unwrap(),
+            b"*:*:*:admin:hunter2\n"
+        );
+        assert!(matches!(
+            render("{{ missing }}", &item("hunter2")),
+            Err(Error::MissingField(_))
+        ));
+        assert!(matches!(
+            render("{{ password", &item("hunter2")),
+            Err(Error::InvalidTemplate(_))
+        ));
+    }
+
+    #[test]
+    fn test_apply() {
+        let dir = std::env::temp_dir().join(format!("op_connect_rs-sink-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let marker = dir.join("reloaded");
+        let sink = FileSink::new(&item("").vault, "2fcbqwe9ndg175zg2dzwftvkpa")
+            .target(SinkTarget::field(dir.join("password"), "password").with_mode(0o640))
+            .target(SinkTarget::file(dir.join("ca.pem"), "ca.pem"))
+            .with_reload_command(["touch", marker.to_str().unwrap()]);
+        let files = HashMap::from([(
+            "6r65pjq33banznomn7q22sj44e".to_owned(),
+            Zeroizing::new(b"cert".to_vec()),
+        )]);
+
+        assert_eq!(
+            sink.file_ids(&item("")).unwrap(),
+            vec!["6r65pjq33banznomn7q22sj44e"]
+        );
+        assert!(sink.apply(&item("hunter2"), &files).unwrap());
+        assert_eq!(fs::read_to_string(dir.join("password")).unwrap(), "hunter2");
+        assert_eq!(fs::read(dir.join("ca.pem")).unwrap(), b"cert");
+        assert!(marker.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dir.join("password"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+
+        assert!(unchanged(&dir.join("password"), b"hunter2"));
+        assert!(!unchanged(&dir.join("password"), b"hunter3"));
+        assert!(!unchanged(&dir.join("password"), b"hunter"));
+        assert!(!unchanged(&dir.join("missing"), b""));
+
+        // Nothing changed, so nothing is written or reloaded.
+        fs::remove_file(&marker).unwrap();
+        assert!(!sink.apply(&item("hunter2"), &files).unwrap());
+        assert!(!marker.exists());
+
+        // A missing attachment fails before anything is written.
+        assert!(sink.apply(&item("rotated"), &HashMap::new()).is_err());
+        assert_eq!(fs::read_to_string(dir.join("password")).unwrap(), "hunter2");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,552 @@
+//! Secret stores.
+//!
+//! The [SecretStore] trait covers what most applications
+//! need from Connect: listing vaults and items, fetching an item, and
+//! resolving a secret reference.  Code written against it can run on:
+//! - [OpClient](crate::OpClient), which talks to Connect;
+//! - [InMemoryStore], built from [Item] fixtures, for tests;
+//! - [EnvStore], which reads references from environment variables;
+//! - [ChainStore], which tries stores in order, so that,
+//!   for example, environment variables override Connect during local
+//!   development.
+//!
+//! The trait is async for the async client and blocking for the blocking
+//! one.  A store that does not have a vault, item or reference answers
+//! `Error::NotFound`, which is what lets a `ChainStore` move on to the next
+//! store.  So does a reference to a field that has no value.
+//! ```rust,ignore
+//! let store = ChainStore::new()
+//!     .with_store(EnvStore::new())
+//!     .with_store(client);
+//! // OP_DEV__POSTGRES__PASSWORD, if it is set, or else Connect.
+//! let password = store.get_reference("op://dev/Postgres/password").await?;
+//! ```
+use std::collections::HashMap;
+#[cfg(not(feature = "blocking"))]
+use std::{future::Future, pin::Pin};
+
+use zeroize::Zeroizing;
+
+use crate::model::secret_reference::unique_match;
+use crate::{Error, FieldValue, Item, ItemOverview, OpClient, Result, SecretReference, Vault};
+
+/// A future returned by a [SecretStore].
+#[cfg(not(feature = "blocking"))]
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Read access to vaults, items and secret references.  See the
+/// [store](crate::store) module.
+pub trait SecretStore: Send + Sync {
+    #[cfg(not(feature = "blocking"))]
+    fn get_vaults(&self) -> StoreFuture<'_, Vec<Vault>>;
+    #[cfg(feature = "blocking")]
+    fn get_vaults(&self) -> Result<Vec<Vault>>;
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_items<'a>(&'a self, vault: &'a Vault) -> StoreFuture<'a, Vec<ItemOverview>>;
+    #[cfg(feature = "blocking")]
+    fn get_items(&self, vault: &Vault) -> Result<Vec<ItemOverview>>;
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_item_detail<'a>(&'a self, vault: &'a Vault, item_id: &'a str) -> StoreFuture<'a, Item>;
+    #[cfg(feature = "blocking")]
+    fn get_item_detail(&self, vault: &Vault, item_id: &str) -> Result<Item>;
+
+    /// The value of the field named by `op://vault/item/[section/]field`.
+    #[cfg(not(feature = "blocking"))]
+    fn get_reference<'a>(&'a self, reference: &'a str) -> StoreFuture<'a, FieldValue>;
+    /// The value of the field named by `op://vault/item/[section/]field`.
+    #[cfg(feature = "blocking")]
+    fn get_reference(&self, reference: &str) -> Result<FieldValue>;
+}
+
+impl SecretStore for OpClient {
+    #[cfg(not(feature = "blocking"))]
+    fn get_vaults(&self) -> StoreFuture<'_, Vec<Vault>> {
+        Box::pin(OpClient::get_vaults(self))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_vaults(&self) -> Result<Vec<Vault>> {
+        OpClient::get_vaults(self)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_items<'a>(&'a self, vault: &'a Vault) -> StoreFuture<'a, Vec<ItemOverview>> {
+        Box::pin(OpClient::get_items(self, vault))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_items(&self, vault: &Vault) -> Result<Vec<ItemOverview>> {
+        OpClient::get_items(self, vault)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_item_detail<'a>(&'a self, vault: &'a Vault, item_id: &'a str) -> StoreFuture<'a, Item> {
+        Box::pin(OpClient::get_item_detail(self, vault, item_id))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_item_detail(&self, vault: &Vault, item_id: &str) -> Result<Item> {
+        OpClient::get_item_detail(self, vault, item_id)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_reference<'a>(&'a self, reference: &'a str) -> StoreFuture<'a, FieldValue> {
+        Box::pin(OpClient::get_reference(self, reference))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_reference(&self, reference: &str) -> Result<FieldValue> {
+        OpClient::get_reference(self, reference)
+    }
+}
+
+/// A store holding vaults and items in memory.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryStore {
+    vaults: Vec<Vault>,
+    items: Vec<Item>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_vault(mut self, vault: Vault) -> Self {
+        self.vaults.retain(|v| v.id != vault.id);
+        self.vaults.push(vault);
+        self
+    }
+
+    /// Add an item to the vault named by `item.vault.id`.
+    pub fn with_item(mut self, item: Item) -> Self {
+        self.items.retain(|i| i.id != item.id);
+        self.items.push(item);
+        self
+    }
+
+    pub(crate) fn vaults(&self) -> Result<Vec<Vault>> {
+        Ok(self.vaults.clone())
+    }
+
+    pub(crate) fn items(&self, vault: &Vault) -> Result<Vec<ItemOverview>> {
+        if !self.vaults.iter().any(|v| v.id == vault.id) {
+            return Err(Error::NotFound);
+        }
+        Ok(self
+            .items
+            .iter()
+            .filter(|item| item.vault.id == vault.id)
+            .map(ItemOverview::from)
+            .collect())
+    }
+
+    pub(crate) fn item(&self, vault: &Vault, item_id: &str) -> Result<Item> {
+        self.items
+            .iter()
+            .find(|item| item.vault.id == vault.id && item.id == item_id)
+            .cloned()
+            .ok_or(Error::NotFound)
+    }
+
+    pub(crate) fn reference(&self, reference: &str) -> Result<FieldValue> {
+        let reference: SecretReference = reference.parse()?;
+        let vault = unique_match(
+            self.vaults.iter().filter(|v| reference.matches_vault(v)),
+            &reference,
+        )?;
+        let item = unique_match(
+            self.items.iter().filter(|item| {
+                item.vault.id == vault.id
+                    && (item.id == reference.item || item.title == reference.item)
+            }),
+            &reference,
+        )?;
+        reference.field(item)?.value.clone().ok_or(Error::NotFound)
+    }
+}
+
+/// A store that reads secret references from environment variables.  It
+/// holds no vaults or items.
+///
+/// A reference is read from the variable mapped to it with
+/// [EnvStore::with_var], or else from its prefix followed by the upper-cased
+/// parts of the reference joined by `__`.  Within a part, each run of
+/// anything but letters and digits becomes a single `_`, and `_` is trimmed
+/// from both ends.  With the default prefix, `op://dev/Postgres/admin
+/// password` is read from `OP_DEV__POSTGRES__ADMIN_PASSWORD`.
+///
+/// References that differ only in punctuation or case, such as
+/// `op://dev/db/admin-password` and `op://dev/DB/admin password`, share a
+/// variable; map one of them with [EnvStore::with_var].
+#[derive(Debug, Clone)]
+pub struct EnvStore {
+    prefix: String,
+    vars: HashMap<SecretReference, String>,
+}
+
+impl Default for EnvStore {
+    fn default() -> Self {
+        Self {
+            prefix: "OP_".to_owned(),
+            vars: HashMap::new(),
+        }
+    }
+}
+
+impl EnvStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix of derived variable names.  Defaults to `OP_`.
+    pub fn with_prefix(self, prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_owned(),
+            ..self
+        }
+    }
+
+    /// Read `reference` from `var`.  References are compared once parsed,
+    /// so `op://dev/db/admin%20password` and `op://dev/db/admin password` are
+    /// the same.  An invalid reference is ignored.
+    pub fn with_var(mut self, reference: &str, var: &str) -> Self {
+        match reference.parse() {
+            Ok(reference) => {
+                self.vars.insert(reference, var.to_owned());
+            }
+            Err(e) => log::warn!("ignoring variable {}: {}", var, e),
+        }
+        self
+    }
+
+    /// The variable `reference` is read from.
+    pub fn var_name(&self, reference: &SecretReference) -> String {
+        if let Some(var) = self.vars.get(reference) {
+            return var.clone();
+        }
+        let parts = [&reference.vault, &reference.item]
+            .into_iter()
+            .chain(&reference.section)
+            .chain([&reference.field]);
+        let mut name = self.prefix.clone();
+        for (i, part) in parts.enumerate() {
+            if i > 0 {
+                name.push_str("__");
+            }
+            let words = part
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|word| !word.is_empty());
+            for (j, word) in words.enumerate() {
+                if j > 0 {
+                    name.push('_');
+                }
+                name.push_str(&word.to_ascii_uppercase());
+            }
+        }
+        name
+    }
+
+    pub(crate) fn reference(&self, reference: &str) -> Result<FieldValue> {
+        let reference: SecretReference = reference.parse()?;
+        let value = std::env::var(self.var_name(&reference)).map_err(|_| Error::NotFound)?;
+        let value = Zeroizing::new(value);
+        Ok(FieldValue::new(&value))
+    }
+}
+
+impl SecretStore for InMemoryStore {
+    #[cfg(not(feature = "blocking"))]
+    fn get_vaults(&self) -> StoreFuture<'_, Vec<Vault>> {
+        Box::pin(std::future::ready(self.vaults()))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_vaults(&self) -> Result<Vec<Vault>> {
+        self.vaults()
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_items<'a>(&'a self, vault: &'a Vault) -> StoreFuture<'a, Vec<ItemOverview>> {
+        Box::pin(std::future::ready(self.items(vault)))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_items(&self, vault: &Vault) -> Result<Vec<ItemOverview>> {
+        self.items(vault)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_item_detail<'a>(&'a self, vault: &'a Vault, item_id: &'a str) -> StoreFuture<'a, Item> {
+        Box::pin(std::future::ready(self.item(vault, item_id)))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_item_detail(&self, vault: &Vault, item_id: &str) -> Result<Item> {
+        self.item(vault, item_id)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_reference<'a>(&'a self, reference: &'a str) -> StoreFuture<'a, FieldValue> {
+        Box::pin(std::future::ready(self.reference(reference)))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_reference(&self, reference: &str) -> Result<FieldValue> {
+        self.reference(reference)
+    }
+}
+
+impl SecretStore for EnvStore {
+    #[cfg(not(feature = "blocking"))]
+    fn get_vaults(&self) -> StoreFuture<'_, Vec<Vault>> {
+        Box::pin(std::future::ready(Ok(Vec::new())))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_vaults(&self) -> Result<Vec<Vault>> {
+        Ok(Vec::new())
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_items<'a>(&'a self, _vault: &'a Vault) -> StoreFuture<'a, Vec<ItemOverview>> {
+        Box::pin(std::future::ready(Err(Error::NotFound)))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_items(&self, _vault: &Vault) -> Result<Vec<ItemOverview>> {
+        Err(Error::NotFound)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_item_detail<'a>(
+        &'a self,
+        _vault: &'a Vault,
+        _item_id: &'a str,
+    ) -> StoreFuture<'a, Item> {
+        Box::pin(std::future::ready(Err(Error::NotFound)))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_item_detail(&self, _vault: &Vault, _item_id: &str) -> Result<Item> {
+        Err(Error::NotFound)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_reference<'a>(&'a self, reference: &'a str) -> StoreFuture<'a, FieldValue> {
+        Box::pin(std::future::ready(self.reference(reference)))
+    }
+    #[cfg(feature = "blocking")]
+    fn get_reference(&self, reference: &str) -> Result<FieldValue> {
+        self.reference(reference)
+    }
+}
+
+/// Stores tried in order.  Items and references come from the first store
+/// that has them; vault and item lists are merged, earlier stores first.
+/// See the [store](crate::store) module.
+#[derive(Default)]
+pub struct ChainStore {
+    stores: Vec<Box<dyn SecretStore>>,
+}
+
+impl ChainStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_store(mut self, store: impl SecretStore + 'static) -> Self {
+        self.stores.push(Box::new(store));
+        self
+    }
+}
+
+impl std::fmt::Debug for ChainStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChainStore")
+            .field("stores", &self.stores.len())
+            .finish()
+    }
+}
+
+impl SecretStore for ChainStore {
+    #[cfg(not(feature = "blocking"))]
+    fn get_vaults(&self) -> StoreFuture<'_, Vec<Vault>> {
+        Box::pin(async move {
+            let mut vaults = Vec::new();
+            for store in &self.stores {
+                merge(&mut vaults, store.get_vaults().await, |v| &v.id)?;
+            }
+            Ok(vaults)
+        })
+    }
+    #[cfg(feature = "blocking")]
+    fn get_vaults(&self) -> Result<Vec<Vault>> {
+        let mut vaults = Vec::new();
+        for store in &self.stores {
+            merge(&mut vaults, store.get_vaults(), |v| &v.id)?;
+        }
+        Ok(vaults)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_items<'a>(&'a self, vault: &'a Vault) -> StoreFuture<'a, Vec<ItemOverview>> {
+        Box::pin(async move {
+            let mut items = Vec::new();
+            for store in &self.stores {
+                merge(&mut items, store.get_items(vault).await, |i| &i.id)?;
+            }
+            Ok(items)
+        })
+    }
+    #[cfg(feature = "blocking")]
+    fn get_items(&self, vault: &Vault) -> Result<Vec<ItemOverview>> {
+        let mut items = Vec::new();
+        for store in &self.stores {
+            merge(&mut items, store.get_items(vault), |i| &i.id)?;
+        }
+        Ok(items)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_item_detail<'a>(&'a self, vault: &'a Vault, item_id: &'a str) -> StoreFuture<'a, Item> {
+        Box::pin(async move {
+            for store in &self.stores {
+                match store.get_item_detail(vault, item_id).await {
+                    Err(Error::NotFound) => continue,
+                    result => return result,
+                }
+            }
+            Err(Error::NotFound)
+        })
+    }
+    #[cfg(feature = "blocking")]
+    fn get_item_detail(&self, vault: &Vault, item_id: &str) -> Result<Item> {
+        for store in &self.stores {
+            match store.get_item_detail(vault, item_id) {
+                Err(Error::NotFound) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::NotFound)
+    }
+
+    #[cfg(not(feature = "blocking"))]
+    fn get_reference<'a>(&'a self, reference: &'a str) -> StoreFuture<'a, FieldValue> {
+        Box::pin(async move {
+            for store in &self.stores {
+                match store.get_reference(reference).await {
+                    Err(Error::NotFound) => continue,
+                    result => return result,
+                }
+            }
+            Err(Error::NotFound)
+        })
+    }
+    #[cfg(feature = "blocking")]
+    fn get_reference(&self, reference: &str) -> Result<FieldValue> {
+        for store in &self.stores {
+            match store.get_reference(reference) {
+                Err(Error::NotFound) => continue,
+                result => return result,
+            }
+        }
+        Err(Error::NotFound)
+    }
+}
+
+/// Vaults or items from several stores, without duplicates.  A store that
+/// does not have the vault is skipped; any other error is returned.
+fn merge<T>(merged: &mut Vec<T>, result: Result<Vec<T>>, id: impl Fn(&T) -> &str) -> Result<()> {
+    let found = match result {
+        Err(Error::NotFound) => return Ok(()),
+        result => result?,
+    };
+    for value in found {
+        if !merged.iter().any(|m| id(m) == id(&value)) {
+            merged.push(value);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn store() -> InMemoryStore {
+        InMemoryStore::new()
+            .with_vault(
+                serde_json::from_str(include_str!("../tests/fixtures/dev_vault.json")).unwrap(),
+            )
+            .with_item(
+                serde_json::from_str(include_str!("../tests/fixtures/okta_item.json")).unwrap(),
+            )
+    }
+
+    #[test]
+    fn test_in_memory() {
+        let store = store();
+        let vault = &store.vaults().unwrap()[0];
+        let items = store.items(vault).unwrap();
+        assert_eq!(items[0].title, "okta");
+        assert_eq!(store.item(vault, &items[0].id).unwrap().version, 2);
+        assert_eq!(
+            store
+                .reference("op://dev/okta/app")
+                .unwrap()
+                .expose_secret(),
+            "dev-123456.okta.com"
+        );
+        assert!(matches!(
+            store.reference("op://dev/stripe/key"),
+            Err(Error::NotFound)
+        ));
+
+        let mut okta = store.items[0].clone();
+        for field in okta.fields.iter_mut().flatten() {
+            field.value = None;
+        }
+        let store = store.with_item(okta);
+        assert!(matches!(
+            store.reference("op://dev/okta/app"),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_env_var_name() {
+        let store = EnvStore::new()
+            .with_var("op://prod/db/password", "DATABASE_PASSWORD")
+            .with_var("op://prod/db/admin%20password", "DATABASE_ADMIN_PASSWORD");
+        let name = |reference: &str| store.var_name(&reference.parse().unwrap());
+        assert_eq!(
+            name("op://dev/Postgres/admin%20password"),
+            "OP_DEV__POSTGRES__ADMIN_PASSWORD"
+        );
+        assert_eq!(name("op://dev/db/Admin/pin"), "OP_DEV__DB__ADMIN__PIN");
+        assert_eq!(name("op://dev/db-admin/pin"), "OP_DEV__DB_ADMIN__PIN");
+        assert_eq!(
+            name("op://dev/db/--admin  password!/pin"),
+            "OP_DEV__DB__ADMIN_PASSWORD__PIN"
+        );
+        // Only punctuation and case differ.
+        assert_eq!(
+            name("op://dev/db/admin-password"),
+            name("op://dev/DB/admin%20password")
+        );
+        assert_eq!(name("op://prod/db/password"), "DATABASE_PASSWORD");
+        assert_eq!(
+            name("op://prod/db/admin password"),
+            "DATABASE_ADMIN_PASSWORD"
+        );
+        assert_eq!(
+            name("op://prod/db/admin%20password"),
+            "DATABASE_ADMIN_PASSWORD"
+        );
+        assert!(matches!(
+            store.reference("op://op-connect-rs-test/unset/var"),
+            Err(Error::NotFound)
+        ));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut merged = vec!["a".to_owned()];
+        merge(&mut merged, Ok(vec!["a".to_owned(), "b".to_owned()]), |s| s).unwrap();
+        merge(&mut merged, Err(Error::NotFound), |s| s).unwrap();
+        assert_eq!(merged, vec!["a", "b"]);
+        assert!(merge(&mut merged, Err(Error::InvalidToken), |s| s).is_err());
+    }
+}
@@ -0,0 +1,405 @@
+//! Password strength assessment.
+//!
+//! Entropy is estimated from the character classes in the password, then
+//! reduced for predictable parts: dates, sequences, keyboard runs and
+//! repeats.  Passwords on the bundled common password list are always
+//! [Score::VeryWeak].
+//! ```rust
+//! use op_connect_rs::{evaluate_password, Score};
+//!
+//! assert_eq!(evaluate_password("P@ssw0rd").score, Score::VeryWeak);
+//! assert!(evaluate_password("t7#Rq!9zLm@2vXc$").score >= Score::Strong);
+//! ```
+use serde::Serialize;
+
+use crate::{Field, FieldPurpose, FieldType, FieldValue, Item};
+
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+const KEYBOARD_ROWS: [&str; 4] = ["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"];
+
+/// Bits assumed for a recognizable date, about 200 years of days.
+const DATE_ENTROPY: f64 = 16.0;
+
+/// Overall rating, from the estimated entropy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Score {
+    /// Under 28 bits, or a common password.
+    VeryWeak,
+    /// Under 36 bits.
+    Weak,
+    /// Under 60 bits.
+    Fair,
+    /// Under 128 bits.
+    Strong,
+    VeryStrong,
+}
+
+impl Score {
+    fn from_entropy(entropy: f64) -> Self {
+        match entropy {
+            e if e < 28.0 => Score::VeryWeak,
+            e if e < 36.0 => Score::Weak,
+            e if e < 60.0 => Score::Fair,
+            e if e < 128.0 => Score::Strong,
+            _ => Score::VeryStrong,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PatternKind {
+    /// The same character three or more times, or a repeated chunk.
+    Repeat,
+    /// Three or more characters counting up or down, such as "abc" or "987".
+    Sequence,
+    /// Four or more adjacent keys, such as "qwer".
+    Keyboard,
+    /// A year or full date, such as "1987" or "2024-03-14".
+    Date,
+}
+
+/// A predictable part of a password.  Positions are in characters.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub start: usize,
+    pub len: usize,
+}
+
+/// Result of [evaluate_password].  Never contains the password itself.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordStrength {
+    /// Estimated entropy in bits, after allowing for patterns.
+    pub entropy: f64,
+    pub score: Score,
+    /// Whether the password is on the common password list, ignoring case
+    /// and trailing digits or symbols.
+    pub common: bool,
+    pub patterns: Vec<Pattern>,
+    /// The entropy reported by Connect for the field, if any.
+    pub reported_entropy: Option<f64>,
+}
+
+/// Assess the strength of a password.
+pub fn evaluate_password(password: &str) -> PasswordStrength {
+    let chars: Vec<char> = password.chars().collect();
+    let pool = pool_size(&chars);
+    let bits_per_char = if pool > 1 { (pool as f64).log2() } else { 0.0 };
+
+    let mut covered = vec![false; chars.len()];
+    let mut patterns = Vec::new();
+    find_dates(&chars, &mut covered, &mut patterns);
+    find_sequences(&chars, &mut covered, &mut patterns);
+    find_repeats(&chars, &mut covered, &mut patterns);
+
+    let free = covered.iter().filter(|c| !**c).count();
+    let mut entropy = free as f64 * bits_per_char;
+    for pattern in &patterns {
+        entropy += match pattern.kind {
+            PatternKind::Date => DATE_ENTROPY,
+            // Which character to start from, and how long the run is.
+            PatternKind::Repeat => bits_per_char + (pattern.len as f64).log2(),
+            // As above, plus the direction.
+            PatternKind::Sequence | PatternKind::Keyboard => {
+                bits_per_char + (pattern.len as f64).log2() + 1.0
+            }
+        };
+    }
+
+    let common = is_common(password);
+    if common {
+        entropy = entropy.min((COMMON_PASSWORDS.lines().count() as f64).log2());
+    }
+
+    PasswordStrength {
+        entropy,
+        score: if common {
+            Score::VeryWeak
+        } else {
+            Score::from_entropy(entropy)
+        },
+        common,
+        patterns,
+        reported_entropy: None,
+    }
+}
+
+impl FieldValue {
+    /// Assess the value as a password.
+    pub fn strength(&self) -> PasswordStrength {
+        evaluate_password(self.inner())
+    }
+}
+
+impl Field {
+    /// Assess the field value as a password, including the entropy reported
+    /// by Connect.  None if the field has no value.
+    pub fn strength(&self) -> Option<PasswordStrength> {
+        let mut strength = self.value.as_ref()?.strength();
+        strength.reported_entropy = self.entropy;
+        Some(strength)
+    }
+}
+
+impl Item {
+    /// Assess the item's password: the field with the PASSWORD purpose, else
+    /// the field with id "password", else the first concealed field.
+    pub fn password_strength(&self) -> Option<PasswordStrength> {
+        self.password_field()?.strength()
+    }
+
+    /// The item's password: the field with the PASSWORD purpose, else the
+    /// field with id "password", else the first concealed field.
+    pub(crate) fn password_field(&self) -> Option<&Field> {
+        let fields = self.fields.as_ref()?;
+        fields
+            .iter()
+            .find(|field| field.purpose == Some(FieldPurpose::Password))
+            .or_else(|| fields.iter().find(|field| field.id == "password"))
+            .or_else(|| {
+                fields
+                    .iter()
+                    .find(|field| field._type == FieldType::Concealed)
+            })
+    }
+}
+
+fn pool_size(chars: &[char]) -> usize {
+    let mut pool = 0;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        pool += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        pool += 100;
+    }
+    pool
+}
+
+fn is_common(password: &str) -> bool {
+    let lower = password.to_lowercase();
+    let stripped = lower.trim_end_matches(|c: char| c.is_ascii_digit() || c.is_ascii_punctuation());
+    COMMON_PASSWORDS
+        .lines()
+        .any(|common| common == lower || (stripped.len() >= 4 && common == stripped))
+}
+
+fn mark(
+    covered: &mut [bool],
+    patterns: &mut Vec<Pattern>,
+    kind: PatternKind,
+    start: usize,
+    len: usize,
+) {
+    covered[start..start + len]
+        .iter_mut()
+        .for_each(|c| *c = true);
+    patterns.push(Pattern { kind, start, len });
+}
+
+fn is_free(covered: &[bool], start: usize, len: usize) -> bool {
+    covered[start..start + len].iter().all(|c| !c)
+}
+
+/// Years from 1900 to 2099, and YYYYMMDD, DDMMYYYY or MMDDYYYY dates with
+/// optional separators.
+fn find_dates(chars: &[char], covered: &mut [bool], patterns: &mut Vec<Pattern>) {
+    let mut i = 0;
+    while i < chars.len() {
+        if !chars[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < chars.len() && (chars[i].is_ascii_digit() || "-/.".contains(chars[i])) {
+            i += 1;
+        }
+        let digits: String = chars[start..i]
+            .iter()
+            .filter(|c| c.is_ascii_digit())
+            .collect();
+        let len = chars[start..i]
+            .iter()
+            .rposition(|c| c.is_ascii_digit())
+            .map_or(0, |p| p + 1);
+        if digits.len() == 8 && is_full_date(&digits) {
+            mark(covered, patterns, PatternKind::Date, start, len);
+        } else {
+            // Look for a year inside a longer run of digits.
+            for offset in 0..(start + len).saturating_sub(start + 3) {
+                let at = start + offset;
+                let year: String = chars[at..at + 4].iter().collect();
+                if year.chars().all(|c| c.is_ascii_digit())
+                    && is_year(&year)
+                    && is_free(covered, at, 4)
+                {
+                    mark(covered, patterns, PatternKind::Date, at, 4);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn is_year(s: &str) -> bool {
+    s.parse::<u32>().is_ok_and(|y| (1900..=2099).contains(&y))
+}
+
+fn is_full_date(digits: &str) -> bool {
+    let part = |r: std::ops::Range<usize>| digits[r].parse::<u32>().unwrap_or(0);
+    let valid = |month: u32, day: u32| (1..=12).contains(&month) && (1..=31).contains(&day);
+    (is_year(&digits[..4]) && valid(part(4..6), part(6..8)))
+        || (is_year(&digits[4..])
+            && (valid(part(2..4), part(0..2)) || valid(part(0..2), part(2..4))))
+}
+
+fn find_sequences(chars: &[char], covered: &mut [bool], patterns: &mut Vec<Pattern>) {
+    // Keyboard runs first, since "1234" is both.
+    let lower: Vec<char> = chars.iter().map(|c| c.to_ascii_lowercase()).collect();
+    let mut i = 0;
+    while i + 4 <= lower.len() {
+        let longest = KEYBOARD_ROWS
+            .iter()
+            .flat_map(|row| {
+                let reversed: String = row.chars().rev().collect();
+                [row.to_string(), reversed]
+            })
+            .map(|row| {
+                let row: Vec<char> = row.chars().collect();
+                (0..row.len())
+                    .map(|r| {
+                        row[r..]
+                            .iter()
+                            .zip(&lower[i..])
+                            .take_while(|(a, b)| a == b)
+                            .count()
+                    })
+                    .max()
+                    .unwrap_or(0)
+            })
+            .max()
+            .unwrap_or(0);
+        if longest >= 4 && is_free(covered, i, longest) {
+            mark(covered, patterns, PatternKind::Keyboard, i, longest);
+            i += longest;
+        } else {
+            i += 1;
+        }
+    }
+
+    let mut i = 0;
+    while i + 3 <= chars.len() {
+        let delta = chars[i + 1] as i64 - chars[i] as i64;
+        let mut len = 1;
+        if delta == 1 || delta == -1 {
+            while i + len < chars.len()
+                && chars[i + len] as i64 - chars[i + len - 1] as i64 == delta
+            {
+                len += 1;
+            }
+        }
+        if len >= 3 && is_free(covered, i, len) {
+            mark(covered, patterns, PatternKind::Sequence, i, len);
+            i += len;
+        } else {
+            i += 1;
+        }
+    }
+}
+
+fn find_repeats(chars: &[char], covered: &mut [bool], patterns: &mut Vec<Pattern>) {
+    // The whole password made of one repeated chunk, such as "abcabcabc".
+    for unit in 2..=chars.len() / 2 {
+        if chars.len().is_multiple_of(unit)
+            && chars.chunks(unit).all(|chunk| chunk == &chars[..unit])
+            && is_free(covered, unit, chars.len() - unit)
+        {
+            mark(
+                covered,
+                patterns,
+                PatternKind::Repeat,
+                unit,
+                chars.len() - unit,
+            );
+            return;
+        }
+    }
+
+    let mut i = 0;
+    while i < chars.len() {
+        let len = chars[i..].iter().take_while(|c| **c == chars[i]).count();
+        if len >= 3 && is_free(covered, i, len) {
+            mark(covered, patterns, PatternKind::Repeat, i, len);
+        }
+        i += len;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn kinds(password: &str) -> Vec<PatternKind> {
+        evaluate_password(password)
+            .patterns
+            .iter()
+            .map(|p| p.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_common() {
+        assert!(evaluate_password("password").common);
+        assert!(evaluate_password("Password123!").common);
+        assert!(evaluate_password("HUNTER2").common);
+        assert!(!evaluate_password("hunter-correct-staple").common);
+        assert_eq!(evaluate_password("letmein").score, Score::VeryWeak);
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert_eq!(kinds("xabcdx"), vec![PatternKind::Sequence]);
+        assert_eq!(kinds("x9876x"), vec![PatternKind::Keyboard]);
+        assert_eq!(kinds("zzzzzz!Q"), vec![PatternKind::Repeat]);
+        assert_eq!(kinds("Xy!1987"), vec![PatternKind::Date]);
+        assert_eq!(kinds("Xy!2024-03-14"), vec![PatternKind::Date]);
+        assert_eq!(kinds("asdfgh!R"), vec![PatternKind::Keyboard]);
+        assert!(kinds("t7#Rq!9zLm@2vXc$").is_empty());
+    }
+
+    #[test]
+    fn test_patterns_lower_entropy() {
+        let random = evaluate_password("kQ7!mZ2@");
+        let repeated = evaluate_password("kQ7!kQ7!");
+        assert!(repeated.entropy < random.entropy);
+        assert!(evaluate_password("aaaaaaaaaaaaaaaaaaaa").score <= Score::Weak);
+    }
+
+    #[test]
+    fn test_item_password_strength() {
+        let item = fixtures::item(serde_json::json!({
+            "category": "LOGIN",
+            "fields": [
+                {"id": "username", "type": "STRING", "purpose": "USERNAME", "label": "username", "value": "wendy"},
+                {
+                    "id": "password", "type": "CONCEALED", "purpose": "PASSWORD", "label": "password",
+                    "value": "hLDegPkuMQqyQiyDZqRdWGoojiN5KYQtXuA0wBDe9z3Caj6FQGHpbGu",
+                    "entropy": 189.78359985351562
+                }
+            ]
+        }));
+        let strength = item.password_strength().expect("no password");
+        assert_eq!(strength.score, Score::VeryStrong);
+        assert_eq!(strength.reported_entropy, Some(189.78359985351562));
+    }
+}
@@ -0,0 +1,684 @@
+//! An in-process Connect server for tests.
+//!
+//! [MockServer] listens on a random local port and implements the Connect
+//! endpoints the client uses: vaults, items with `title` and `name` filters,
+//! item create, replace, patch and delete, and files.  It is seeded with
+//! [Vault] and [Item] fixtures, checks the bearer token, records every
+//! request, and can inject faults.  Items are versioned like on a real
+//! server: each write bumps the item's `version` and the vault's
+//! `content_version`.
+//! ```rust,ignore
+//! let server = MockServer::start();
+//! server.add_vault(vault);
+//! server.add_item(item);
+//! server.inject(Fault::Status(429));
+//! let client = server.client();
+//! assert!(client.get_vaults().await.is_err());
+//! assert_eq!(client.get_vaults().await?.len(), 1);
+//! assert_eq!(server.requests().len(), 2);
+//! ```
+//! Requests are served on their own threads, so the server works with both
+//! the async and the blocking client.
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+use chrono::Utc;
+use percent_encoding::percent_decode_str;
+use rand::{rngs::OsRng, Rng};
+use serde_json::{json, Value};
+
+use crate::{Item, OpClient, Vault};
+
+/// Token the server accepts unless set otherwise.
+pub const MOCK_TOKEN: &str = "mock-connect-token";
+
+/// A fault to inject into a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fault {
+    /// Wait, then answer normally.
+    Latency(Duration),
+    /// Answer with this status and a Connect error body, such as 429 or 500.
+    Status(u16),
+    /// Answer 200 with a body that is not JSON.
+    MalformedJson,
+}
+
+/// A request received by a [MockServer].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    /// The path, such as `/v1/vaults`, without the query.
+    pub path: String,
+    /// The decoded query, without the `?`.
+    pub query: Option<String>,
+    /// Whether the request carried the expected bearer token.
+    pub authorized: bool,
+    pub body: Vec<u8>,
+}
+
+/// Called with a vault and its items after each write.
+type WriteHook = Box<dyn Fn(&Vault, &[&Item]) + Send + Sync>;
+
+struct MockState {
+    token: String,
+    vaults: Vec<Vault>,
+    items: Vec<Item>,
+    /// File contents, by file id.
+    files: HashMap<String, Vec<u8>>,
+    requests: Vec<RecordedRequest>,
+    recording: bool,
+    faults: VecDeque<Fault>,
+    latency: Duration,
+    on_write: Option<WriteHook>,
+    /// Requests being handled right now, and the most there have been at
+    /// once since requests were last cleared.
+    in_flight: usize,
+    max_in_flight: usize,
+}
+
+/// An in-process Connect server.  See the [testing](crate::testing) module.
+/// The server stops when dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl MockServer {
+    /// Start an empty server on a random local port.
+    pub fn start() -> Self {
+        Self::bind("127.0.0.1:0").expect("failed to bind the mock server")
+    }
+
+    /// Start an empty server on `addr`.
+    pub fn bind(addr: impl ToSocketAddrs) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            token: MOCK_TOKEN.to_owned(),
+            vaults: Vec::new(),
+            items: Vec::new(),
+            files: HashMap::new(),
+            requests: Vec::new(),
+            recording: true,
+            faults: VecDeque::new(),
+            latency: Duration::ZERO,
+            on_write: None,
+            in_flight: 0,
+            max_in_flight: 0,
+        }));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let server = Self {
+            addr,
+            state: state.clone(),
+            stopped: stopped.clone(),
+        };
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let state = state.clone();
+                thread::spawn(move || {
+                    let _ = serve(stream, &state);
+                });
+            }
+        });
+        Ok(server)
+    }
+
+    /// The base URL of the API, ending in `/v1/`.
+    pub fn url(&self) -> String {
+        format!("http://{}/v1/", self.addr)
+    }
+
+    /// A client for this server, with the token it accepts.
+    pub fn client(&self) -> OpClient {
+        let token = self.state().token.clone();
+        OpClient::new(&self.url(), &token).expect("failed to create client")
+    }
+
+    /// Accept `token` in place of [MOCK_TOKEN].
+    pub fn set_token(&self, token: &str) {
+        self.state().token = token.to_owned();
+    }
+
+    pub fn add_vault(&self, vault: Vault) {
+        let mut state = self.state();
+        state.vaults.retain(|v| v.id != vault.id);
+        state.vaults.push(vault);
+    }
+
+    /// Add an item to the vault named by `item.vault.id`, which must have
+    /// been added first.
+    pub fn add_item(&self, item: Item) {
+        let mut state = self.state();
+        assert!(
+            state.vaults.iter().any(|v| v.id == item.vault.id),
+            "add vault {} before its items",
+            item.vault.id
+        );
+        state.items.retain(|i| i.id != item.id);
+        state.items.push(item);
+    }
+
+    /// Serve `content` for the file with this id.
+    pub fn add_file_content(&self, file_id: &str, content: &[u8]) {
+        self.state()
+            .files
+            .insert(file_id.to_owned(), content.to_vec());
+    }
+
+    /// The current state of an item, after any writes made through the API.
+    pub fn item(&self, item_id: &str) -> Option<Item> {
+        self.state().items.iter().find(|i| i.id == item_id).cloned()
+    }
+
+    /// Apply `fault` to the next request.  Faults queue up, one per request.
+    pub fn inject(&self, fault: Fault) {
+        self.state().faults.push_back(fault);
+    }
+
+    /// Delay every response by `latency`.
+    pub fn set_latency(&self, latency: Duration) {
+        self.state().latency = latency;
+    }
+
+    /// Every request received so far, oldest first.
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state().requests.clone()
+    }
+
+    /// Forget the requests received so far, and reset
+    /// [max_in_flight](MockServer::max_in_flight).
+    pub fn clear_requests(&self) {
+        let mut state = self.state();
+        state.requests.clear();
+        state.max_in_flight = state.in_flight;
+    }
+
+    /// The most requests handled at once since the server started, or since
+    /// requests were last cleared.
+    pub fn max_in_flight(&self) -> usize {
+        self.state().max_in_flight
+    }
+
+    /// Stop or resume recording requests.  Recording is on by default; a
+    /// long-running server should turn it off.
+    pub fn set_recording(&self, recording: bool) {
+        self.state().recording = recording;
+    }
+
+    /// Call `hook` with the vault and its items after every write through
+    /// the API, while the server holds its lock.
+    pub fn on_write(&self, hook: impl Fn(&Vault, &[&Item]) + Send + Sync + 'static) {
+        self.state().on_write = Some(Box::new(hook));
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // Wake the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+impl std::fmt::Debug for MockServer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MockServer")
+            .field("addr", &self.addr)
+            .finish_non_exhaustive()
+    }
+}
+
+struct Response {
+    status: u16,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &impl serde::Serialize) -> Self {
+        Self {
+            status,
+            body: serde_json::to_vec(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({"status": status, "message": message}))
+    }
+}
+
+/// Counts a request as handled once dropped.
+struct InFlight<'a>(&'a Mutex<MockState>);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).in_flight -= 1;
+    }
+}
+
+/// Read one request, answer it, and close the connection.
+fn serve(stream: TcpStream, state: &Mutex<MockState>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_owned();
+    let target = parts.next().unwrap_or_default().to_owned();
+
+    let mut content_length = 0;
+    let mut authorization = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => content_length = value.trim().parse().unwrap_or(0),
+                "authorization" => authorization = Some(value.trim().to_owned()),
+                _ => {}
+            }
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body)?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_owned(), Some(decode(query))),
+        None => (target, None),
+    };
+    let (fault, latency) = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        let authorized = authorization.as_deref() == Some(&format!("Bearer {}", state.token));
+        if state.recording {
+            state.requests.push(RecordedRequest {
+                method: method.clone(),
+                path: path.clone(),
+                query: query.clone(),
+                authorized,
+                body: body.clone(),
+            });
+        }
+        state.in_flight += 1;
+        state.max_in_flight = state.max_in_flight.max(state.in_flight);
+        (state.faults.pop_front(), state.latency)
+    };
+    let _in_flight = InFlight(state);
+
+    thread::sleep(latency);
+    let response = match fault {
+        Some(Fault::Status(status)) => Response::error(status, "injected fault"),
+        Some(Fault::MalformedJson) => Response {
+            status: 200,
+            body: b"{\"id\": ".to_vec(),
+        },
+        fault => {
+            if let Some(Fault::Latency(latency)) = fault {
+                thread::sleep(latency);
+            }
+            let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+            if authorization.as_deref() == Some(&format!("Bearer {}", state.token)) {
+                route(&mut state, &method, &path, query.as_deref(), &body)
+            } else {
+                Response::error(401, "Invalid bearer token")
+            }
+        }
+    };
+
+    let mut stream = stream;
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason(response.status),
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+fn route(
+    state: &mut MockState,
+    method: &str,
+    path: &str,
+    query: Option<&str>,
+    body: &[u8],
+) -> Response {
+    let Some(path) = path.strip_prefix("/v1/") else {
+        return Response::error(404, "Not found");
+    };
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    match (method, segments.as_slice()) {
+        ("GET", ["vaults"]) => match filter(query, "name") {
+            Ok(name) => Response::json(
+                200,
+                &state
+                    .vaults
+                    .iter()
+                    .filter(|v| name.as_ref().is_none_or(|name| &v.name == name))
+                    .collect::<Vec<_>>(),
+            ),
+            Err(response) => response,
+        },
+        ("GET", ["vaults", vault_id]) => match state.vaults.iter().find(|v| v.id == *vault_id) {
+            Some(vault) => Response::json(200, vault),
+            None => Response::error(404, "Vault not found"),
+        },
+        (_, ["vaults", vault_id, "items", ..])
+            if !state.vaults.iter().any(|v| v.id == *vault_id) =>
+        {
+            Response::error(404, "Vault not found")
+        }
+        ("GET", ["vaults", vault_id, "items"]) => match filter(query, "title") {
+            Ok(title) => Response::json(
+                200,
+                &state
+                    .items
+                    .iter()
+                    .filter(|i| i.vault.id == *vault_id)
+                    .filter(|i| title.as_ref().is_none_or(|title| &i.title == title))
+                    .map(overview)
+                    .collect::<Vec<_>>(),
+            ),
+            Err(response) => response,
+        },
+        ("POST", ["vaults", vault_id, "items"]) => {
+            let Ok(mut item) = serde_json::from_slice::<Value>(body) else {
+                return Response::error(400, "Invalid item");
+            };
+            item["id"] = json!(random_id());
+            item["vault"] = json!({"id": vault_id});
+            item["version"] = json!(0);
+            item["createdAt"] = json!(Utc::now());
+            item["updatedAt"] = json!(Utc::now());
+            item["lastEditedBy"] = json!("MOCKSERVER");
+            store(state, vault_id, None, item)
+        }
+        (_, ["vaults", vault_id, "items", item_id, ..])
+            if !state
+                .items
+                .iter()
+                .any(|i| i.id == *item_id && i.vault.id == *vault_id) =>
+        {
+            Response::error(404, "Item not found")
+        }
+        ("GET", ["vaults", _, "items", item_id]) => Response::json(200, &find(state, item_id)),
+        ("PUT", ["vaults", vault_id, "items", item_id]) => {
+            let Ok(mut item) = serde_json::from_slice::<Value>(body) else {
+                return Response::error(400, "Invalid item");
+            };
+            item["id"] = json!(item_id);
+            item["vault"] = json!({"id": vault_id});
+            store(state, vault_id, Some(item_id), item)
+        }
+        ("PATCH", ["vaults", vault_id, "items", item_id]) => {
+            let Ok(operations) = serde_json::from_slice::<Vec<Value>>(body) else {
+                return Response::error(400, "Invalid patch");
+            };
+            let mut item = serde_json::to_value(find(state, item_id)).unwrap_or_default();
+            for operation in &operations {
+                if let Err(message) = patch(&mut item, operation) {
+                    return Response::error(400, &message);
+                }
+            }
+            store(state, vault_id, Some(item_id), item)
+        }
+        ("DELETE", ["vaults", vault_id, "items", item_id]) => {
+            state.items.retain(|i| i.id != *item_id);
+            bump_vault(state, vault_id);
+            Response {
+                status: 204,
+                body: Vec::new(),
+            }
+        }
+        ("GET", ["vaults", _, "items", item_id, "files"]) => {
+            Response::json(200, &find(state, item_id).files.unwrap_or_default())
+        }
+        ("GET", ["vaults", _, "items", item_id, "files", file_id, rest @ ..]) => {
+            let item = find(state, item_id);
+            let Some(file) = item.files.iter().flatten().find(|f| f.id == *file_id) else {
+                return Response::error(404, "File not found");
+            };
+            match rest {
+                [] => Response::json(200, file),
+                ["content"] => match state.files.get(*file_id) {
+                    Some(content) => Response {
+                        status: 200,
+                        body: content.clone(),
+                    },
+                    None => Response::error(404, "File content not found"),
+                },
+                _ => Response::error(404, "Not found"),
+            }
+        }
+        _ => Response::error(404, "Not found"),
+    }
+}
+
+/// The value of a `filter=<attribute> eq "<value>"` query.  The name filter
+/// is also accepted as `name="<value>"`.
+fn filter(query: Option<&str>, attribute: &str) -> Result<Option<String>, Response> {
+    let Some(query) = query else {
+        return Ok(None);
+    };
+    let expression = query
+        .strip_prefix("filter=")
+        .and_then(|filter| filter.strip_prefix(attribute))
+        .and_then(|rest| rest.trim_start().strip_prefix("eq"))
+        .or_else(|| query.strip_prefix(attribute)?.strip_prefix('='));
+    expression
+        .map(|value| value.trim().trim_matches('"').to_owned())
+        .map(Some)
+        .ok_or_else(|| Response::error(400, "Invalid filter"))
+}
+
+fn find(state: &MockState, item_id: &str) -> Item {
+    state
+        .items
+        .iter()
+        .find(|i| i.id == item_id)
+        .cloned()
+        .expect("item was checked")
+}
+
+/// An item without its fields, files and sections, as items are listed.
+fn overview(item: &Item) -> Value {
+    let mut value = serde_json::to_value(item).unwrap_or_default();
+    if let Some(object) = value.as_object_mut() {
+        for key in ["fields", "files", "sections"] {
+            object.remove(key);
+        }
+    }
+    value
+}
+
+/// Store a created or updated item as the next version.
+fn store(
+    state: &mut MockState,
+    vault_id: &str,
+    item_id: Option<&str>,
+    mut item: Value,
+) -> Response {
+    let version = item_id
+        .and_then(|id| state.items.iter().find(|i| i.id == id))
+        .map_or(0, |old| old.version);
+    item["version"] = json!(version + 1);
+    item["updatedAt"] = json!(Utc::now());
+    let item: Item = match serde_json::from_value(item) {
+        Ok(item) => item,
+        Err(e) => return Response::error(400, &format!("Invalid item: {}", e)),
+    };
+    state.items.retain(|i| i.id != item.id);
+    state.items.push(item.clone());
+    bump_vault(state, vault_id);
+    Response::json(if item_id.is_some() { 200 } else { 201 }, &item)
+}
+
+/// Record a write to the vault.
+fn bump_vault(state: &mut MockState, vault_id: &str) {
+    let Some(vault) = state.vaults.iter_mut().find(|v| v.id == vault_id) else {
+        return;
+    };
+    vault.content_version = Some(vault.content_version.unwrap_or(0) + 1);
+    vault.updated_at = Some(Utc::now());
+    if let Some(on_write) = &state.on_write {
+        let vault = state.vaults.iter().find(|v| v.id == vault_id);
+        let items: Vec<&Item> = state
+            .items
+            .iter()
+            .filter(|i| i.vault.id == vault_id)
+            .collect();
+        on_write(vault.expect("vault was found"), &items);
+    }
+}
+
+/// Apply one JSON Patch operation.  Array elements may be addressed by
+/// index, or by `id` as Connect does for fields and sections.
+fn patch(item: &mut Value, operation: &Value) -> Result<(), String> {
+    let op = operation["op"].as_str().unwrap_or_default();
+    let path = operation["path"].as_str().unwrap_or_default();
+    let mut tokens: Vec<&str> = path.split('/').skip(1).collect();
+    let last = tokens
+        .pop()
+        .ok_or_else(|| format!("Invalid path {}", path))?;
+    let mut target = item;
+    for token in tokens {
+        target = child(target, token).ok_or_else(|| format!("Invalid path {}", path))?;
+    }
+    let value = operation.get("value").cloned();
+    match (op, target) {
+        ("add" | "replace", Value::Object(object)) => {
+            if op == "replace" && !object.contains_key(last) {
+                return Err(format!("Invalid path {}", path));
+            }
+            object.insert(last.to_owned(), value.ok_or("Missing value")?);
+        }
+        ("add", Value::Array(array)) if last == "-" => array.push(value.ok_or("Missing value")?),
+        ("add" | "replace" | "remove", Value::Array(array)) => {
+            let index = position(array, last).ok_or_else(|| format!("Invalid path {}", path))?;
+            match op {
+                "remove" => {
+                    array.remove(index);
+                }
+                _ => array[index] = value.ok_or("Missing value")?,
+            }
+        }
+        ("remove", Value::Object(object)) => {
+            object
+                .remove(last)
+                .ok_or_else(|| format!("Invalid path {}", path))?;
+        }
+        _ => return Err(format!("Invalid operation {} {}", op, path)),
+    }
+    Ok(())
+}
+
+fn child<'a>(value: &'a mut Value, token: &str) -> Option<&'a mut Value> {
+    match value {
+        Value::Object(object) => object.get_mut(token),
+        Value::Array(array) => {
+            let index = position(array, token)?;
+            array.get_mut(index)
+        }
+        _ => None,
+    }
+}
+
+fn position(array: &[Value], token: &str) -> Option<usize> {
+    token
+        .parse::<usize>()
+        .ok()
+        .filter(|index| *index < array.len())
+        .or_else(|| array.iter().position(|v| v["id"] == token))
+}
+
+fn decode(query: &str) -> String {
+    percent_decode_str(&query.replace('+', " "))
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+fn random_id() -> String {
+    const ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+    (0..26)
+        .map(|_| ALPHABET[OsRng.gen_range(0..ALPHABET.len())] as char)
+        .collect()
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_patch() {
+        let mut item = json!({
+            "title": "db",
+            "fields": [{"id": "password", "value": "hunter2"}, {"id": "notes", "value": ""}]
+        });
+        for operation in [
+            json!({"op": "replace", "path": "/fields/password/value", "value": "correct horse"}),
+            json!({"op": "remove", "path": "/fields/1"}),
+            json!({"op": "add", "path": "/fields/-", "value": {"id": "pin", "value": "1234"}}),
+            json!({"op": "replace", "path": "/title", "value": "postgres"}),
+        ] {
+            patch(&mut item, &operation).unwrap();
+        }
+        assert_eq!(
+            item,
+            json!({
+                "title": "postgres",
+                "fields": [{"id": "password", "value": "correct horse"}, {"id": "pin", "value": "1234"}]
+            })
+        );
+        assert!(patch(
+            &mut item,
+            &json!({"op": "replace", "path": "/fields/missing/value", "value": ""})
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_filter() {
+        assert_eq!(filter(None, "name").ok(), Some(None));
+        assert_eq!(
+            filter(Some("filter=title eq \"db\""), "title").ok(),
+            Some(Some("db".to_owned()))
+        );
+        assert_eq!(
+            filter(Some("name=\"dev\""), "name").ok(),
+            Some(Some("dev".to_owned()))
+        );
+        assert!(filter(Some("filter=tag eq \"x\""), "title").is_err());
+    }
+}
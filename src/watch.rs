@@ -0,0 +1,213 @@
+//! Change watching.
+//!
+//! `OpClient::watch` polls a vault's `content_version` and, when it moves,
+//! lists the vault and refetches only the watched items whose `version` or
+//! `updated_at` changed.  Each changed item is reported once as an
+//! [ItemChanged].  Polls are `interval` apart, plus up to `jitter` so that
+//! many services started together do not poll in lockstep.
+//!
+//! Watching bypasses the response caches, and drops cached copies of the
+//! items it sees change.
+//!
+//! Connect reports a `content_version` for every vault, but if it is ever
+//! missing there is nothing to compare, and every poll lists the whole vault.
+use std::collections::HashMap;
+use std::time::Duration;
+
+use rand::{rngs::OsRng, Rng};
+
+use crate::{Field, Item, ItemOverview, Vault};
+
+/// Default time between polls.
+pub const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(30);
+/// Default upper bound of the random delay added to each poll.
+pub const DEFAULT_WATCH_JITTER: Duration = Duration::from_secs(5);
+
+/// A watched item that changed.
+#[derive(Debug, Clone)]
+pub struct ItemChanged {
+    pub old_version: u32,
+    pub new: Item,
+    /// Ids of fields whose value changed, or that were added or removed.
+    pub changed_fields: Vec<String>,
+}
+
+pub(crate) struct WatchState {
+    pub vault_id: String,
+    pub interval: Duration,
+    pub jitter: Duration,
+    items: HashMap<String, Item>,
+    /// Unknown until the first poll, so that changes made before the watch
+    /// started are picked up.
+    content_version: Option<u32>,
+}
+
+impl WatchState {
+    pub fn new(vault: &Vault, items: &[Item]) -> Self {
+        Self {
+            vault_id: vault.id.clone(),
+            interval: DEFAULT_WATCH_INTERVAL,
+            jitter: DEFAULT_WATCH_JITTER,
+            items: items
+                .iter()
+                .map(|item| (item.id.clone(), item.clone()))
+                .collect(),
+            content_version: None,
+        }
+    }
+
+    /// Time to wait before the next poll.
+    pub fn delay(&self) -> Duration {
+        if self.jitter.is_zero() {
+            return self.interval;
+        }
+        self.interval + OsRng.gen_range(Duration::ZERO..=self.jitter)
+    }
+
+    /// Whether the vault may have changed since the last poll, which is
+    /// always the case before the first one, or if the vault has no
+    /// `content_version`.
+    pub fn vault_changed(&self, vault: &Vault) -> bool {
+        self.content_version.is_none() || vault.content_version != self.content_version
+    }
+
+    /// Ids of watched items that moved on since they were last seen.
+    pub fn moved(&self, overviews: &[ItemOverview]) -> Vec<String> {
+        overviews
+            .iter()
+            .filter(|overview| match self.items.get(&overview.id) {
+                Some(item) => {
+                    overview
+                        .version
+                        .is_some_and(|version| version != item.version)
+                        || overview.updated_at != item.updated_at
+                }
+                None => false,
+            })
+            .map(|overview| overview.id.clone())
+            .collect()
+    }
+
+    /// Record the refetched items and the vault version they were read at.
+    pub fn apply(&mut self, items: Vec<Item>, content_version: Option<u32>) -> Vec<ItemChanged> {
+        self.content_version = content_version;
+        items
+            .into_iter()
+            .filter_map(|new| {
+                let old = self.items.insert(new.id.clone(), new.clone())?;
+                if old.version == new.version && old.updated_at == new.updated_at {
+                    return None;
+                }
+                Some(ItemChanged {
+                    old_version: old.version,
+                    changed_fields: changed_fields(&old, &new),
+                    new,
+                })
+            })
+            .collect()
+    }
+}
+
+fn changed_fields(old: &Item, new: &Item) -> Vec<String> {
+    fn value(field: &Field) -> Option<&str> {
+        field.value.as_ref().map(|value| value.expose_secret())
+    }
+    let old_fields: HashMap<&str, &Field> = old
+        .fields
+        .iter()
+        .flatten()
+        .map(|field| (field.id.as_str(), field))
+        .collect();
+    let new_fields: HashMap<&str, &Field> = new
+        .fields
+        .iter()
+        .flatten()
+        .map(|field| (field.id.as_str(), field))
+        .collect();
+
+    let mut changed: Vec<String> = new_fields
+        .iter()
+        .filter(|(id, field)| match old_fields.get(*id) {
+            Some(old) => value(old) != value(field),
+            None => true,
+        })
+        .chain(
+            old_fields
+                .iter()
+                .filter(|(id, _)| !new_fields.contains_key(*id)),
+        )
+        .map(|(id, _)| id.to_string())
+        .collect();
+    changed.sort();
+    changed
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::fixtures;
+
+    fn item(version: u32, password: &str, extra: bool) -> Item {
+        let mut fields = vec![
+            serde_json::json!({"id": "username", "type": "STRING", "label": "username", "value": "admin"}),
+            serde_json::json!({"id": "password", "type": "CONCEALED", "label": "password", "value": password}),
+        ];
+        if extra {
+            fields.push(serde_json::json!({"id": "notes", "type": "STRING", "label": "notes", "value": "x"}));
+        }
+        fixtures::item(serde_json::json!({
+            "vault": {"id": "2me2svdjkmsh5xmqodspni4h6a"},
+            "version": version,
+            "updatedAt": format!("2020-01-0{}T00:00:00Z", version),
+            "fields": fields
+        }))
+    }
+
+    fn overview(item: &Item) -> ItemOverview {
+        serde_json::from_value(serde_json::to_value(item).unwrap()).unwrap()
+    }
+
+    fn vault() -> Vault {
+        serde_json::from_str(include_str!("../tests/fixtures/dev_vault.json")).unwrap()
+    }
+
+    #[test]
+    fn test_watch_state() {
+        let vault = vault();
+        let mut state = WatchState::new(&vault, &[item(1, "hunter2", false)]);
+        assert!(state.vault_changed(&vault));
+        assert!(state
+            .moved(&[overview(&item(1, "hunter2", false))])
+            .is_empty());
+        assert!(state.apply(Vec::new(), Some(3)).is_empty());
+        assert!(!state.vault_changed(&vault));
+
+        let rotated = item(2, "correct horse", true);
+        assert_eq!(state.moved(&[overview(&rotated)]), vec![rotated.id.clone()]);
+        let changes = state.apply(vec![rotated], Some(4));
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].old_version, 1);
+        assert_eq!(changes[0].new.version, 2);
+        assert_eq!(changes[0].changed_fields, vec!["notes", "password"]);
+
+        // Without a content version, every poll has to list the vault.
+        let unversioned = Vault {
+            content_version: None,
+            ..vault
+        };
+        state.apply(Vec::new(), unversioned.content_version);
+        assert!(state.vault_changed(&unversioned));
+    }
+
+    #[test]
+    fn test_delay() {
+        let vault = vault();
+        let mut state = WatchState::new(&vault, &[]);
+        state.interval = Duration::from_secs(10);
+        state.jitter = Duration::from_secs(2);
+        for _ in 0..100 {
+            let delay = state.delay();
+            assert!(delay >= state.interval && delay <= state.interval + state.jitter);
+        }
+    }
+}